@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snss::{NavigationEntry, PickleIterator};
+
+fuzz_target!(|data: &[u8]| {
+    // Prepend a valid header so the input reaches the field decoders.
+    let mut pickle = (data.len() as u32).to_le_bytes().to_vec();
+    pickle.extend_from_slice(data);
+
+    if let Ok(mut pickle) = PickleIterator::new(pickle, 4) {
+        let _ = NavigationEntry::from_pickle(&mut pickle);
+    }
+});
@@ -0,0 +1,27 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snss::PickleIterator;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut pickle) = PickleIterator::new(data.to_vec(), 4) else {
+        return;
+    };
+
+    // Cycle through the readers so every length-prefixed type gets to
+    // interpret arbitrary prefixes.
+    for step in 0.. {
+        let before = pickle.remaining();
+        let _ = match step % 6 {
+            0 => pickle.read_string().map(drop),
+            1 => pickle.read_string16().map(drop),
+            2 => pickle.read_string16_lossy().map(drop),
+            3 => pickle.read_bool().map(drop),
+            4 => pickle.read_int64().map(drop),
+            _ => pickle.read_uint16().map(drop),
+        };
+        if pickle.remaining() == 0 || (step % 6 == 5 && pickle.remaining() == before) {
+            break;
+        }
+    }
+});
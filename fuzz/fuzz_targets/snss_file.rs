@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snss::{SnssFile, SnssFileType};
+
+fuzz_target!(|data: &[u8]| {
+    for file_type in [SnssFileType::Session, SnssFileType::Tab] {
+        if let Ok(mut snss_file) = SnssFile::new(file_type, data) {
+            for command in snss_file.iter_session_commands() {
+                let _ = command;
+            }
+        }
+    }
+
+    if let Ok((mut snss_file, _)) = SnssFile::detect(data) {
+        for command in snss_file.iter_session_commands() {
+            let _ = command;
+        }
+    }
+});
@@ -0,0 +1,183 @@
+//! `#[derive(FromPickle, ToPickle)]` for the traits in the `snss` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Error, Fields, Type, parse_macro_input};
+
+#[proc_macro_derive(FromPickle, attributes(pickle))]
+pub fn derive_from_pickle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_pickle(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(ToPickle, attributes(pickle))]
+pub fn derive_to_pickle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_pickle(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct Field {
+    /// Field name, or the tuple index as a member expression.
+    member: syn::Member,
+    optional: bool,
+    string16: bool,
+}
+
+fn parse_fields(input: &DeriveInput) -> syn::Result<(Vec<Field>, bool)> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "pickle derives only support structs",
+        ));
+    };
+
+    let named = matches!(data.fields, Fields::Named(_));
+    let mut fields = Vec::new();
+    let mut seen_optional = false;
+    for (index, field) in data.fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(index.into()),
+        };
+
+        let mut string16 = false;
+        for attr in &field.attrs {
+            if !attr.path().is_ident("pickle") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("string16") {
+                    string16 = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown pickle attribute"))
+                }
+            })?;
+        }
+
+        let optional = is_option(&field.ty);
+        if seen_optional && !optional {
+            return Err(Error::new(
+                field.span(),
+                "optional pickle fields must come after all required fields",
+            ));
+        }
+        seen_optional |= optional;
+
+        fields.push(Field {
+            member,
+            optional,
+            string16,
+        });
+    }
+    Ok((fields, named))
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+fn expand_from_pickle(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let (fields, named) = parse_fields(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let bindings: Vec<_> = (0..fields.len())
+        .map(|index| format_ident!("field_{}", index))
+        .collect();
+    let reads = fields.iter().zip(&bindings).map(|(field, binding)| {
+        let read = match (field.string16, field.optional) {
+            (false, _) => quote! { ::snss::FromPickle::from_pickle(pickle)? },
+            (true, false) => quote! { pickle.read_string16()? },
+            (true, true) => quote! {
+                if pickle.remaining() == 0 {
+                    ::std::option::Option::None
+                } else {
+                    ::std::option::Option::Some(pickle.read_string16()?)
+                }
+            },
+        };
+        quote! { let #binding = #read; }
+    });
+
+    let construct = if named {
+        let members = fields.iter().map(|field| &field.member);
+        quote! { Self { #(#members: #bindings),* } }
+    } else {
+        quote! { Self(#(#bindings),*) }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::snss::FromPickle for #name #ty_generics #where_clause {
+            fn from_pickle(
+                pickle: &mut ::snss::PickleIterator,
+            ) -> ::std::result::Result<Self, ::snss::PickleError> {
+                #(#reads)*
+                ::std::result::Result::Ok(#construct)
+            }
+        }
+    })
+}
+
+fn expand_to_pickle(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let (fields, _) = parse_fields(input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let required = fields.iter().filter(|field| !field.optional).map(|field| {
+        let member = &field.member;
+        if field.string16 {
+            quote! { pickle.write_string16(&self.#member); }
+        } else {
+            quote! { ::snss::ToPickle::to_pickle(&self.#member, pickle); }
+        }
+    });
+    // Trailing fields are written up to the first `None`; anything after
+    // it could not be told apart from the missing field when reading.
+    let optional = fields.iter().filter(|field| field.optional).map(|field| {
+        let member = &field.member;
+        let write = if field.string16 {
+            quote! { pickle.write_string16(value); }
+        } else {
+            quote! { ::snss::ToPickle::to_pickle(value, pickle); }
+        };
+        quote! {
+            let ::std::option::Option::Some(value) = &self.#member else {
+                break 'trailing;
+            };
+            #write
+        }
+    });
+    let optional: Vec<_> = optional.collect();
+    let writes = if optional.is_empty() {
+        quote! { #(#required)* }
+    } else {
+        quote! {
+            #(#required)*
+            'trailing: {
+                #(#optional)*
+            }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::snss::ToPickle for #name #ty_generics #where_clause {
+            fn to_pickle(&self, pickle: &mut ::snss::PickleWriter) {
+                #writes
+            }
+        }
+    })
+}
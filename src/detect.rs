@@ -0,0 +1,159 @@
+use crate::{NavigationEntry, PickleIterator, SnssFileType};
+
+/// Result of inferring whether a file holds session or tab-restore commands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub file_type: SnssFileType,
+    /// Share of the evidence that agrees with `file_type`, from 0.0 (no
+    /// evidence either way) to 1.0 (every scored command agreed).
+    pub confidence: f32,
+}
+
+/// Tallies evidence from individual commands.
+///
+/// Both file types share the command id space, so an id alone is rarely
+/// conclusive. Navigation commands are the strongest signal: session files
+/// use id 6 and tab-restore files id 1, and either one only counts when
+/// the payload really decodes as a navigation pickle. The remaining
+/// commands are scored by id and payload size.
+#[derive(Debug, Default)]
+pub(crate) struct Detector {
+    session: u32,
+    tab: u32,
+}
+
+const NAVIGATION_WEIGHT: u32 = 3;
+
+impl Detector {
+    pub(crate) fn add(&mut self, command_id: u8, payload: &[u8]) {
+        if is_navigation_pickle(payload) {
+            match command_id {
+                6 => self.session += NAVIGATION_WEIGHT,
+                1 => self.tab += NAVIGATION_WEIGHT,
+                _ => {}
+            }
+            return;
+        }
+
+        let size = payload.len();
+        match command_id {
+            // SetTabWindow, SetTabIndexInWindow, SetSelectedNavigationIndex,
+            // SetSelectedTabInIndex, SetWindowType, SetPinnedState.
+            0 | 2 | 7 | 8 | 9 | 12 if size == 8 => self.session += 1,
+            // Window bounds.
+            1 | 10 | 14 if size == 24 => self.session += 1,
+            // TabClosed, WindowClosed, LastActiveTime.
+            16 | 17 | 21 if size == 16 => self.session += 1,
+            // SetActiveWindow.
+            20 if size == 4 => self.session += 1,
+            // Tab-restore ids stop at 14.
+            15..=254 => self.session += 1,
+            // RestoredEntry.
+            2 if size == 4 => self.tab += 1,
+            // SelectedNavigationInTab, with and without a timestamp.
+            4 if size == 8 || size == 16 => self.tab += 1,
+            // PinnedState.
+            5 if size == 1 => self.tab += 1,
+            // Window.
+            9 if is_pickle(payload) => self.tab += 1,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn finish(&self) -> Detection {
+        let total = self.session + self.tab;
+        if total == 0 {
+            return Detection {
+                file_type: SnssFileType::Session,
+                confidence: 0.0,
+            };
+        }
+
+        let (file_type, score) = if self.tab > self.session {
+            (SnssFileType::Tab, self.tab)
+        } else {
+            (SnssFileType::Session, self.session)
+        };
+        Detection {
+            file_type,
+            confidence: score as f32 / total as f32,
+        }
+    }
+}
+
+fn is_pickle(payload: &[u8]) -> bool {
+    payload.len() >= 4
+        && u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize + 4
+            == payload.len()
+}
+
+fn is_navigation_pickle(payload: &[u8]) -> bool {
+    if !is_pickle(payload) {
+        return false;
+    }
+    match PickleIterator::new(payload.to_vec(), 4) {
+        Ok(mut pickle) => NavigationEntry::from_pickle(&mut pickle).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::ChromeTime;
+    use crate::{PickleWriter, SnssError, SnssFile};
+
+    fn detect(commands: &[(u8, &[u8])]) -> Detection {
+        let mut detector = Detector::default();
+        for (command_id, payload) in commands {
+            detector.add(*command_id, payload);
+        }
+        detector.finish()
+    }
+
+    #[test]
+    fn no_evidence_is_a_session_without_confidence() {
+        let detection = detect(&[]);
+        assert_eq!(detection.file_type, SnssFileType::Session);
+        assert_eq!(detection.confidence, 0.0);
+
+        // Unknown tab-restore ids with unexpected sizes score nothing.
+        assert_eq!(detect(&[(3, &[0; 5]), (13, &[0; 2])]).confidence, 0.0);
+
+        let (snss_file, detection) = SnssFile::detect(b"SNSS\x01\0\0\0".as_slice()).unwrap();
+        assert_eq!(snss_file.file_type(), SnssFileType::Session);
+        assert_eq!(detection.confidence, 0.0);
+    }
+
+    #[test]
+    fn ties_go_to_session() {
+        // Id 2 is SetTabIndexInWindow with 8 bytes, RestoredEntry with 4.
+        let detection = detect(&[(2, &[0; 8]), (2, &[0; 4])]);
+        assert_eq!(detection.file_type, SnssFileType::Session);
+        assert_eq!(detection.confidence, 0.5);
+    }
+
+    #[test]
+    fn closed_windows_alone_mark_a_tab_file() {
+        let mut pickle = PickleWriter::new();
+        pickle.write_int32(20);
+        pickle.write_int32(0);
+        pickle.write_int32(2);
+        pickle.write_datetime(ChromeTime::from_internal(13_345_678_901_234_567));
+        let window = pickle.into_bytes();
+
+        let detection = detect(&[(9, &window), (9, &window)]);
+        assert_eq!(detection.file_type, SnssFileType::Tab);
+        assert_eq!(detection.confidence, 1.0);
+    }
+
+    #[test]
+    fn encrypted_versions_are_rejected() {
+        for header in [b"SNSS\x02\0\0\0", b"SNSS\x04\0\0\0"] {
+            assert!(matches!(
+                SnssFile::detect(header.as_slice()),
+                Err(SnssError::UnsupportedVersion(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,510 @@
+//! Differences between two snapshots of the same profile's session.
+//!
+//! Tabs are matched by their GUID when both snapshots have one, since it
+//! survives browser restarts, and otherwise by session id, which only
+//! holds within one browser run: tabs with the same id must also share a
+//! navigation and must not have different GUIDs. Windows are matched by
+//! where their tabs went, falling back to the window id.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::TabGroupId;
+use crate::session::{SessionState, Tab, is_same_tab};
+use crate::time::ChromeTime;
+
+/// One difference between the old and the new snapshot.
+///
+/// Tab ids are those of the snapshot the tab is described from: the new
+/// one, except for closed tabs.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    WindowOpened {
+        window_id: i32,
+    },
+    WindowClosed {
+        window_id: i32,
+    },
+    TabOpened {
+        tab_id: i32,
+        guid: Option<String>,
+        window_id: Option<i32>,
+        index: Option<i32>,
+        url: Option<String>,
+    },
+    TabClosed {
+        tab_id: i32,
+        guid: Option<String>,
+        window_id: Option<i32>,
+        url: Option<String>,
+    },
+    /// The tab changed windows, or changed places with other tabs of its
+    /// window. Tabs that only shifted because others were opened, closed
+    /// or moved around them are not reported.
+    TabMoved {
+        tab_id: i32,
+        guid: Option<String>,
+        from_window_id: Option<i32>,
+        from_index: Option<i32>,
+        to_window_id: Option<i32>,
+        to_index: Option<i32>,
+    },
+    TabRegrouped {
+        tab_id: i32,
+        guid: Option<String>,
+        /// Group id and title, if the tab was in a group.
+        from_group: Option<GroupRef>,
+        to_group: Option<GroupRef>,
+    },
+    TabPinned {
+        tab_id: i32,
+        guid: Option<String>,
+    },
+    TabUnpinned {
+        tab_id: i32,
+        guid: Option<String>,
+    },
+    /// A navigation in the new snapshot of a tab that is in both, with a
+    /// URL and timestamp the old snapshot of the tab does not have.
+    NavigationAdded {
+        tab_id: i32,
+        guid: Option<String>,
+        index: i32,
+        url: String,
+        title: String,
+        timestamp: ChromeTime,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GroupRef {
+    pub id: String,
+    pub title: String,
+}
+
+impl fmt::Display for GroupRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.title.is_empty() {
+            write!(f, "group {}", self.id)
+        } else {
+            write!(f, "group {:?}", self.title)
+        }
+    }
+}
+
+fn place(window_id: Option<i32>, index: Option<i32>) -> String {
+    let window = window_id.map_or_else(|| "?".to_string(), |id| id.to_string());
+    let index = index.map_or_else(|| "?".to_string(), |index| index.to_string());
+    format!("window {} #{}", window, index)
+}
+
+fn group(group: &Option<GroupRef>) -> String {
+    group
+        .as_ref()
+        .map_or_else(|| "no group".to_string(), GroupRef::to_string)
+}
+
+/// One line per change: `+` for additions, `-` for removals and `~` for
+/// changes to a tab.
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::WindowOpened { window_id } => write!(f, "+ window {}", window_id),
+            Change::WindowClosed { window_id } => write!(f, "- window {}", window_id),
+            Change::TabOpened {
+                tab_id,
+                window_id,
+                index,
+                url,
+                ..
+            } => write!(
+                f,
+                "+ tab {} in {}: {}",
+                tab_id,
+                place(*window_id, *index),
+                url.as_deref().unwrap_or_default()
+            ),
+            Change::TabClosed { tab_id, url, .. } => write!(
+                f,
+                "- tab {}: {}",
+                tab_id,
+                url.as_deref().unwrap_or_default()
+            ),
+            Change::TabMoved {
+                tab_id,
+                from_window_id,
+                from_index,
+                to_window_id,
+                to_index,
+                ..
+            } => write!(
+                f,
+                "~ tab {} moved from {} to {}",
+                tab_id,
+                place(*from_window_id, *from_index),
+                place(*to_window_id, *to_index)
+            ),
+            Change::TabRegrouped {
+                tab_id,
+                from_group,
+                to_group,
+                ..
+            } => write!(
+                f,
+                "~ tab {} regrouped from {} to {}",
+                tab_id,
+                group(from_group),
+                group(to_group)
+            ),
+            Change::TabPinned { tab_id, .. } => write!(f, "~ tab {} pinned", tab_id),
+            Change::TabUnpinned { tab_id, .. } => write!(f, "~ tab {} unpinned", tab_id),
+            Change::NavigationAdded {
+                tab_id,
+                index,
+                url,
+                title,
+                ..
+            } => {
+                write!(f, "+ tab {} navigation {}: {}", tab_id, index, url)?;
+                if !title.is_empty() {
+                    write!(f, " ({})", title)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The changes from one snapshot to another, windows first, then tabs in
+/// the order of the new snapshot.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionDiff {
+    changes: Vec<Change>,
+}
+
+/// How a tab is recognised in both snapshots.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum TabKey<'a> {
+    Guid(&'a str),
+    Id(i32),
+}
+
+fn open_tabs(state: &SessionState) -> Vec<&Tab> {
+    let mut tabs: Vec<&Tab> = state
+        .tabs()
+        .filter(|tab| {
+            tab.closed.is_none()
+                && tab
+                    .window_id
+                    .and_then(|id| state.window(id))
+                    .is_none_or(|window| window.closed.is_none())
+        })
+        .collect();
+    tabs.sort_by_key(|tab| (tab.window_id, tab.index.unwrap_or(i32::MAX), tab.id));
+    tabs
+}
+
+fn keys<'a>(tabs: &[&'a Tab], both_have_guids: &HashSet<&str>) -> HashMap<TabKey<'a>, &'a Tab> {
+    tabs.iter()
+        .map(|tab| (key_of(tab, both_have_guids), *tab))
+        .collect()
+}
+
+fn key_of<'a>(tab: &'a Tab, both_have_guids: &HashSet<&str>) -> TabKey<'a> {
+    match tab.guid.as_deref() {
+        Some(guid) if both_have_guids.contains(guid) => TabKey::Guid(guid),
+        _ => TabKey::Id(tab.id),
+    }
+}
+
+fn group_ref(state: &SessionState, id: Option<TabGroupId>) -> Option<GroupRef> {
+    id.map(|id| GroupRef {
+        id: id.to_string(),
+        title: state
+            .group(id)
+            .map(|group| group.title.clone())
+            .unwrap_or_default(),
+    })
+}
+
+fn current_url(tab: &Tab) -> Option<String> {
+    tab.current_navigation()
+        .map(|navigation| navigation.entry.url().to_string())
+}
+
+impl SessionDiff {
+    pub fn between(old: &SessionState, new: &SessionState) -> Self {
+        let old_tabs = open_tabs(old);
+        let new_tabs = open_tabs(new);
+
+        let old_guids: HashSet<&str> = old_tabs
+            .iter()
+            .filter_map(|tab| tab.guid.as_deref())
+            .collect();
+        let both_have_guids: HashSet<&str> = new_tabs
+            .iter()
+            .filter_map(|tab| tab.guid.as_deref())
+            .filter(|guid| old_guids.contains(guid))
+            .collect();
+        let new_by_key = keys(&new_tabs, &both_have_guids);
+        // Old tabs that are still there. A shared id only counts when the
+        // tabs also agree on their history, since ids are reused across
+        // browser runs.
+        let old_by_key: HashMap<TabKey, &Tab> = keys(&old_tabs, &both_have_guids)
+            .into_iter()
+            .filter(|(key, old_tab)| match (key, new_by_key.get(key)) {
+                (TabKey::Guid(_), Some(_)) => true,
+                (TabKey::Id(_), Some(new_tab)) => is_same_tab(old_tab, new_tab),
+                (_, None) => false,
+            })
+            .collect();
+
+        // Windows go where most of their tabs went.
+        let mut votes: HashMap<i32, BTreeMap<i32, usize>> = HashMap::new();
+        for (key, new_tab) in &new_by_key {
+            if let (Some(old_tab), Some(new_window)) = (old_by_key.get(key), new_tab.window_id)
+                && let Some(old_window) = old_tab.window_id
+            {
+                *votes
+                    .entry(old_window)
+                    .or_default()
+                    .entry(new_window)
+                    .or_default() += 1;
+            }
+        }
+        let open_windows = |state: &SessionState| -> Vec<i32> {
+            state
+                .windows()
+                .filter(|window| window.closed.is_none())
+                .map(|window| window.id)
+                .collect()
+        };
+        let old_windows = open_windows(old);
+        let new_windows = open_windows(new);
+        let mut window_map: HashMap<i32, i32> = HashMap::new();
+        for old_window in &old_windows {
+            let voted = votes.get(old_window).and_then(|votes| {
+                votes
+                    .iter()
+                    .max_by_key(|(window, count)| (**count, std::cmp::Reverse(**window)))
+                    .map(|(window, _)| *window)
+            });
+            let target = voted.or_else(|| new_windows.contains(old_window).then_some(*old_window));
+            if let Some(target) = target.filter(|target| !window_map.values().any(|t| t == target))
+            {
+                window_map.insert(*old_window, target);
+            }
+        }
+
+        let mut changes = Vec::new();
+        let mapped: HashSet<i32> = window_map.values().copied().collect();
+        for window_id in &new_windows {
+            if !mapped.contains(window_id) {
+                changes.push(Change::WindowOpened {
+                    window_id: *window_id,
+                });
+            }
+        }
+        for window_id in &old_windows {
+            if !window_map.contains_key(window_id) {
+                changes.push(Change::WindowClosed {
+                    window_id: *window_id,
+                });
+            }
+        }
+
+        let moved = moved_tabs(
+            &old_tabs,
+            &new_tabs,
+            &old_by_key,
+            &both_have_guids,
+            &window_map,
+        );
+        for new_tab in &new_tabs {
+            let key = key_of(new_tab, &both_have_guids);
+            let tab_id = new_tab.id;
+            let guid = new_tab.guid.clone();
+            let Some(old_tab) = old_by_key.get(&key) else {
+                changes.push(Change::TabOpened {
+                    tab_id,
+                    guid,
+                    window_id: new_tab.window_id,
+                    index: new_tab.index,
+                    url: current_url(new_tab),
+                });
+                continue;
+            };
+
+            if moved.contains(&key) {
+                changes.push(Change::TabMoved {
+                    tab_id,
+                    guid: guid.clone(),
+                    from_window_id: old_tab.window_id,
+                    from_index: old_tab.index,
+                    to_window_id: new_tab.window_id,
+                    to_index: new_tab.index,
+                });
+            }
+            if old_tab.group != new_tab.group {
+                changes.push(Change::TabRegrouped {
+                    tab_id,
+                    guid: guid.clone(),
+                    from_group: group_ref(old, old_tab.group),
+                    to_group: group_ref(new, new_tab.group),
+                });
+            }
+            match (old_tab.pinned, new_tab.pinned) {
+                (false, true) => changes.push(Change::TabPinned {
+                    tab_id,
+                    guid: guid.clone(),
+                }),
+                (true, false) => changes.push(Change::TabUnpinned {
+                    tab_id,
+                    guid: guid.clone(),
+                }),
+                _ => {}
+            }
+
+            let seen: HashSet<(&str, ChromeTime)> = old_tab
+                .navigations
+                .iter()
+                .map(|navigation| (navigation.entry.url(), navigation.entry.timestamp()))
+                .collect();
+            for navigation in &new_tab.navigations {
+                let entry = &navigation.entry;
+                if !seen.contains(&(entry.url(), entry.timestamp())) {
+                    changes.push(Change::NavigationAdded {
+                        tab_id,
+                        guid: guid.clone(),
+                        index: entry.index(),
+                        url: entry.url().to_string(),
+                        title: entry.title().to_string(),
+                        timestamp: entry.timestamp(),
+                    });
+                }
+            }
+        }
+
+        for old_tab in &old_tabs {
+            if !old_by_key.contains_key(&key_of(old_tab, &both_have_guids)) {
+                changes.push(Change::TabClosed {
+                    tab_id: old_tab.id,
+                    guid: old_tab.guid.clone(),
+                    window_id: old_tab.window_id,
+                    url: current_url(old_tab),
+                });
+            }
+        }
+
+        SessionDiff { changes }
+    }
+
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn write_text<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for change in &self.changes {
+            writeln!(out, "{}", change)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the changes as a JSON array of objects tagged by `change`.
+    pub fn write_json<W: Write>(&self, out: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(out, &self.changes).map_err(io::Error::from)
+    }
+}
+
+/// Tabs that changed windows, plus, within each window, the fewest tabs
+/// whose moves explain the new order: those outside the longest run of
+/// tabs that kept their relative order.
+fn moved_tabs<'a>(
+    old_tabs: &[&'a Tab],
+    new_tabs: &[&'a Tab],
+    old_by_key: &HashMap<TabKey<'a>, &'a Tab>,
+    both_have_guids: &HashSet<&str>,
+    window_map: &HashMap<i32, i32>,
+) -> HashSet<TabKey<'a>> {
+    let old_rank: HashMap<TabKey, usize> = old_tabs
+        .iter()
+        .enumerate()
+        .map(|(rank, tab)| (key_of(tab, both_have_guids), rank))
+        .collect();
+
+    let mut moved = HashSet::new();
+    let mut stayed: BTreeMap<Option<i32>, Vec<(TabKey, usize)>> = BTreeMap::new();
+    for new_tab in new_tabs {
+        let key = key_of(new_tab, both_have_guids);
+        let Some(old_tab) = old_by_key.get(&key) else {
+            continue;
+        };
+        let old_window = old_tab
+            .window_id
+            .map(|id| window_map.get(&id).copied().unwrap_or(id));
+        if old_window != new_tab.window_id {
+            moved.insert(key);
+        } else {
+            let rank = old_rank[&key];
+            stayed
+                .entry(new_tab.window_id)
+                .or_default()
+                .push((key, rank));
+        }
+    }
+
+    for tabs in stayed.into_values() {
+        let ranks: Vec<usize> = tabs.iter().map(|(_, rank)| *rank).collect();
+        let kept = longest_increasing(&ranks);
+        for (position, (key, _)) in tabs.into_iter().enumerate() {
+            if !kept.contains(&position) {
+                moved.insert(key);
+            }
+        }
+    }
+    moved
+}
+
+/// Positions of one longest strictly increasing subsequence.
+fn longest_increasing(values: &[usize]) -> HashSet<usize> {
+    // tails[k] is the position of the smallest tail of a run of length k + 1.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; values.len()];
+    for (position, value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] < *value);
+        previous[position] = length.checked_sub(1).map(|k| tails[k]);
+        if length == tails.len() {
+            tails.push(position);
+        } else {
+            tails[length] = position;
+        }
+    }
+
+    let mut kept = HashSet::new();
+    let mut position = tails.last().copied();
+    while let Some(current) = position {
+        kept.insert(current);
+        position = previous[current];
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_increasing_run() {
+        let kept = longest_increasing(&[0, 3, 1, 2, 4]);
+        assert_eq!(kept, HashSet::from([0, 2, 3, 4]));
+        assert!(longest_increasing(&[]).is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{SnssFileType, file_name_timestamp, file_name_timestamp_micros};
+
+const SESSIONS_DIR: &str = "Sessions";
+const LEGACY_NAMES: [&str; 4] = [
+    "Current Session",
+    "Last Session",
+    "Current Tabs",
+    "Last Tabs",
+];
+
+/// Whether a file describes the browser's running (or last running)
+/// session or an older one kept around for restore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionKind {
+    Current,
+    Previous,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionFileInfo {
+    pub path: PathBuf,
+    /// Name of the profile directory, e.g. `Default` or `Profile 1`.
+    pub profile: String,
+    pub file_type: SnssFileType,
+    pub kind: SessionKind,
+    /// Timestamp embedded in `Session_<timestamp>` style names, in
+    /// microseconds since 1601-01-01. `None` for the legacy names.
+    pub timestamp: Option<u64>,
+}
+
+impl SessionFileInfo {
+    /// Creation time encoded in the file name.
+    pub fn created(&self) -> Option<SystemTime> {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(file_name_timestamp)
+    }
+}
+
+/// Finds the session files of every profile below a Chromium user-data
+/// directory. A single profile directory is accepted as well.
+///
+/// Files are grouped by profile and type, legacy files first, then the
+/// `Session_`/`Tabs_` files in the order their names say they were
+/// created. The newest file of each type is the current one.
+pub fn discover(user_data_dir: &Path) -> io::Result<Vec<SessionFileInfo>> {
+    let mut profiles = Vec::new();
+    if is_profile_dir(user_data_dir) {
+        profiles.push(user_data_dir.to_path_buf());
+    } else {
+        for entry in fs::read_dir(user_data_dir)? {
+            let path = entry?.path();
+            if path.is_dir() && is_profile_dir(&path) {
+                profiles.push(path);
+            }
+        }
+        profiles.sort();
+    }
+
+    let mut found = Vec::new();
+    for profile in profiles {
+        found.extend(discover_profile(&profile)?);
+    }
+    Ok(found)
+}
+
+fn is_profile_dir(path: &Path) -> bool {
+    path.join(SESSIONS_DIR).is_dir() || LEGACY_NAMES.iter().any(|name| path.join(name).is_file())
+}
+
+fn discover_profile(profile_dir: &Path) -> io::Result<Vec<SessionFileInfo>> {
+    let profile = profile_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut legacy = Vec::new();
+    for name in LEGACY_NAMES {
+        let path = profile_dir.join(name);
+        if path.is_file() {
+            legacy.push((path, name.to_string()));
+        }
+    }
+
+    let mut timestamped = Vec::new();
+    let sessions_dir = profile_dir.join(SESSIONS_DIR);
+    if sessions_dir.is_dir() {
+        for entry in fs::read_dir(&sessions_dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            if let Some(timestamp) = file_name_timestamp_micros(&name) {
+                timestamped.push((path, name, timestamp));
+            }
+        }
+    }
+    timestamped.sort_by(|a, b| a.2.cmp(&b.2).then_with(|| a.0.cmp(&b.0)));
+
+    let mut found = Vec::new();
+    for file_type in [SnssFileType::Session, SnssFileType::Tab] {
+        let newest = timestamped
+            .iter()
+            .rfind(|(_, name, _)| SnssFileType::from_file_name(name) == Some(file_type))
+            .map(|(path, _, _)| path.clone());
+
+        for (path, name) in &legacy {
+            if SnssFileType::from_file_name(name) != Some(file_type) {
+                continue;
+            }
+            // Once timestamped files exist the legacy ones are leftovers
+            // from an older browser version.
+            let kind = if newest.is_none() && name.starts_with("Current ") {
+                SessionKind::Current
+            } else {
+                SessionKind::Previous
+            };
+            found.push(SessionFileInfo {
+                path: path.clone(),
+                profile: profile.clone(),
+                file_type,
+                kind,
+                timestamp: None,
+            });
+        }
+
+        for (path, name, timestamp) in &timestamped {
+            if SnssFileType::from_file_name(name) != Some(file_type) {
+                continue;
+            }
+            let kind = if Some(path) == newest.as_ref() {
+                SessionKind::Current
+            } else {
+                SessionKind::Previous
+            };
+            found.push(SessionFileInfo {
+                path: path.clone(),
+                profile: profile.clone(),
+                file_type,
+                kind,
+                timestamp: Some(*timestamp),
+            });
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn touch(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, b"SNSS\x03\0\0\0").unwrap();
+    }
+
+    fn summary(found: &[SessionFileInfo]) -> Vec<(String, String, SessionKind)> {
+        found
+            .iter()
+            .map(|info| {
+                let name = info
+                    .path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned();
+                (info.profile.clone(), name, info.kind)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn newest_file_of_each_type_is_current() {
+        let dir = TempDir::new("discovery");
+        let sessions = dir.path().join("Default").join(SESSIONS_DIR);
+        for name in [
+            "Session_13345678901234567",
+            "Session_13345678999999999",
+            "Tabs_13345678901234567",
+            "notes.txt",
+        ] {
+            touch(&sessions.join(name));
+        }
+
+        let found = discover(dir.path()).unwrap();
+        use SessionKind::*;
+        assert_eq!(
+            summary(&found),
+            [
+                ("Default", "Session_13345678901234567", Previous),
+                ("Default", "Session_13345678999999999", Current),
+                ("Default", "Tabs_13345678901234567", Current),
+            ]
+            .map(|(profile, name, kind)| (profile.to_string(), name.to_string(), kind))
+        );
+        assert_eq!(found[1].file_type, SnssFileType::Session);
+        assert_eq!(found[2].file_type, SnssFileType::Tab);
+        assert_eq!(found[1].timestamp, Some(13_345_678_999_999_999));
+        assert!(found[1].created().is_some());
+    }
+
+    #[test]
+    fn legacy_names() {
+        let dir = TempDir::new("discovery");
+        let profile = dir.path().join("Default");
+        for name in ["Current Session", "Last Session", "Last Tabs"] {
+            touch(&profile.join(name));
+        }
+
+        use SessionKind::*;
+        let found = discover(&profile).unwrap();
+        assert_eq!(
+            summary(&found),
+            [
+                ("Default", "Current Session", Current),
+                ("Default", "Last Session", Previous),
+                ("Default", "Last Tabs", Previous),
+            ]
+            .map(|(profile, name, kind)| (profile.to_string(), name.to_string(), kind))
+        );
+        assert!(found.iter().all(|info| info.timestamp.is_none()));
+
+        // Timestamped files supersede the legacy ones.
+        touch(&profile.join(SESSIONS_DIR).join("Session_13345678901234567"));
+        let found = discover(&profile).unwrap();
+        assert_eq!(
+            summary(&found)[..2],
+            [
+                ("Default", "Current Session", Previous),
+                ("Default", "Last Session", Previous),
+            ]
+            .map(|(profile, name, kind)| (profile.to_string(), name.to_string(), kind))
+        );
+        assert_eq!(found[2].kind, Current);
+    }
+
+    #[test]
+    fn profiles_in_name_order() {
+        let dir = TempDir::new("discovery");
+        for profile in ["Profile 2", "Default", "Profile 1"] {
+            touch(
+                &dir.path()
+                    .join(profile)
+                    .join(SESSIONS_DIR)
+                    .join("Session_13345678901234567"),
+            );
+        }
+        // Not a profile: no Sessions directory and no legacy files.
+        fs::create_dir_all(dir.path().join("Crashpad")).unwrap();
+
+        let profiles: Vec<String> = discover(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|info| info.profile)
+            .collect();
+        assert_eq!(profiles, ["Default", "Profile 1", "Profile 2"]);
+    }
+}
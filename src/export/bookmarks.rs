@@ -0,0 +1,199 @@
+//! Tabs as a Netscape bookmarks file, which every browser can import.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use super::html::escape;
+use crate::SnssFileType;
+use crate::session::{ClosedEntry, SessionState, Tab};
+use crate::time::ChromeTime;
+
+/// Writes the current page of every tab as a bookmark.
+///
+/// Session files become one folder per window, with a subfolder per tab
+/// group; tabs closed during the session go into a "Closed tabs" folder.
+/// Tab-restore files become a "Recently closed" folder, with a subfolder
+/// for each closed window.
+#[derive(Debug, Clone)]
+pub struct BookmarksExporter {
+    title: String,
+}
+
+impl Default for BookmarksExporter {
+    fn default() -> Self {
+        Self {
+            title: "Recovered tabs".to_string(),
+        }
+    }
+}
+
+/// A folder under construction: its title and its rendered children.
+struct Folder {
+    title: String,
+    date: Option<ChromeTime>,
+    body: String,
+}
+
+impl Folder {
+    fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            date: None,
+            body: String::new(),
+        }
+    }
+
+    fn add_tab(&mut self, tab: &Tab) {
+        let Some(navigation) = tab.current_navigation() else {
+            return;
+        };
+        let entry = &navigation.entry;
+        if entry.url().is_empty() {
+            return;
+        }
+        let title = if entry.title().is_empty() {
+            entry.url()
+        } else {
+            entry.title()
+        };
+        let _ = writeln!(
+            self.body,
+            "<DT><A HREF=\"{}\"{}>{}</A>",
+            escape(entry.url()),
+            add_date(Some(entry.timestamp())),
+            escape(title)
+        );
+    }
+
+    fn add_folder(&mut self, folder: Folder) {
+        if !folder.body.is_empty() {
+            self.body.push_str(&folder.render());
+        }
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "<DT><H3{}>{}</H3>\n<DL><p>\n{}</DL><p>\n",
+            add_date(self.date),
+            escape(&self.title),
+            self.body
+        )
+    }
+}
+
+impl BookmarksExporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Title of the top-level folder holding everything exported.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Writes one bookmarks file covering all the given states, typically
+    /// a session file and the tab-restore file next to it.
+    pub fn write<W: Write>(&self, states: &[&SessionState], mut out: W) -> io::Result<()> {
+        let mut root = Folder::new(self.title.clone());
+        for state in states {
+            match state.file_type() {
+                SnssFileType::Session => add_session(&mut root, state),
+                SnssFileType::Tab => add_closed_entries(&mut root, state),
+            }
+        }
+
+        write!(
+            out,
+            "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+             <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+             <TITLE>Bookmarks</TITLE>\n\
+             <H1>Bookmarks</H1>\n\
+             <DL><p>\n{}</DL><p>\n",
+            root.render()
+        )
+    }
+}
+
+fn add_session(root: &mut Folder, state: &SessionState) {
+    let mut closed = Folder::new("Closed tabs");
+    for window in state.windows() {
+        let mut folder = Folder::new(format!("Window {}", window.id));
+        folder.date = window.closed;
+
+        let tabs = state.tabs_in_window(window.id);
+        let mut groups: Vec<Folder> = Vec::new();
+        let mut group_ids = Vec::new();
+        for tab in tabs {
+            if tab.closed.is_some() {
+                closed.add_tab(tab);
+                continue;
+            }
+            let Some(group_id) = tab.group else {
+                folder.add_tab(tab);
+                continue;
+            };
+            // Groups are contiguous in the tab strip, but keep them together
+            // even when the file says otherwise.
+            let position = match group_ids.iter().position(|id| *id == group_id) {
+                Some(position) => position,
+                None => {
+                    let title = state
+                        .group(group_id)
+                        .map(|group| group.title.as_str())
+                        .filter(|title| !title.is_empty())
+                        .unwrap_or("Unnamed group");
+                    group_ids.push(group_id);
+                    groups.push(Folder::new(title));
+                    groups.len() - 1
+                }
+            };
+            groups[position].add_tab(tab);
+        }
+        for group in groups {
+            folder.add_folder(group);
+        }
+        root.add_folder(folder);
+    }
+
+    // Tabs whose window was never recorded.
+    let mut other = Folder::new("Other tabs");
+    for tab in state
+        .tabs()
+        .filter(|tab| tab.window_id.is_none_or(|id| state.window(id).is_none()))
+    {
+        if tab.closed.is_some() {
+            closed.add_tab(tab);
+        } else {
+            other.add_tab(tab);
+        }
+    }
+    root.add_folder(other);
+    root.add_folder(closed);
+}
+
+fn add_closed_entries(root: &mut Folder, state: &SessionState) {
+    let mut recent = Folder::new("Recently closed");
+    for entry in state.closed_entries() {
+        match entry {
+            ClosedEntry::Tab(tab) => recent.add_tab(tab),
+            ClosedEntry::Window { window, tabs } => {
+                let mut folder = Folder::new(format!("Window {}", window.id));
+                folder.date = window.closed;
+                for tab in tabs {
+                    folder.add_tab(tab);
+                }
+                recent.add_folder(folder);
+            }
+        }
+    }
+    root.add_folder(recent);
+}
+
+/// The `ADD_DATE` attribute, in seconds since the Unix epoch.
+fn add_date(time: Option<ChromeTime>) -> String {
+    match time.filter(|time| !time.is_null()) {
+        Some(time) => format!(" ADD_DATE=\"{}\"", time.unix_micros().div_euclid(1_000_000)),
+        None => String::new(),
+    }
+}
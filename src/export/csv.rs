@@ -0,0 +1,196 @@
+//! One row per navigation, for spreadsheets.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::{NavigationEntry, SessionCommand, SnssError, SnssFile};
+
+/// A column of the export, named in the header row by
+/// [`Column::name`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// Window the tab belonged to; empty when the file never says.
+    WindowId,
+    TabId,
+    Index,
+    Timestamp,
+    Url,
+    Title,
+    Transition,
+    Referrer,
+    HttpStatus,
+}
+
+impl Column {
+    pub const ALL: [Column; 9] = [
+        Column::WindowId,
+        Column::TabId,
+        Column::Index,
+        Column::Timestamp,
+        Column::Url,
+        Column::Title,
+        Column::Transition,
+        Column::Referrer,
+        Column::HttpStatus,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::WindowId => "window_id",
+            Column::TabId => "tab_id",
+            Column::Index => "index",
+            Column::Timestamp => "timestamp",
+            Column::Url => "url",
+            Column::Title => "title",
+            Column::Transition => "transition",
+            Column::Referrer => "referrer",
+            Column::HttpStatus => "http_status",
+        }
+    }
+
+    fn value(self, entry: &NavigationEntry, window_id: Option<i32>) -> String {
+        match self {
+            Column::WindowId => window_id.map(|id| id.to_string()).unwrap_or_default(),
+            Column::TabId => entry.session_id().to_string(),
+            Column::Index => entry.index().to_string(),
+            Column::Timestamp if entry.timestamp().is_null() => String::new(),
+            Column::Timestamp => entry.timestamp().to_string(),
+            Column::Url => entry.url().to_string(),
+            Column::Title => entry.title().to_string(),
+            Column::Transition => entry.transition().to_string(),
+            Column::Referrer => entry.referrer_url().unwrap_or_default().to_string(),
+            Column::HttpStatus => entry
+                .http_status()
+                .map(|status| status.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Unknown column {0:?}")]
+pub struct ParseColumnError(pub String);
+
+impl FromStr for Column {
+    type Err = ParseColumnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Column::ALL
+            .into_iter()
+            .find(|column| column.name() == s)
+            .ok_or_else(|| ParseColumnError(s.to_string()))
+    }
+}
+
+/// Writes navigations as CSV, or TSV with [`CsvExporter::tsv`].
+///
+/// Fields holding the delimiter, quotes or line breaks are quoted, so
+/// titles survive intact.
+#[derive(Debug, Clone)]
+pub struct CsvExporter {
+    columns: Vec<Column>,
+    delimiter: u8,
+}
+
+impl Default for CsvExporter {
+    fn default() -> Self {
+        Self {
+            columns: Column::ALL.to_vec(),
+            delimiter: b',',
+        }
+    }
+}
+
+impl CsvExporter {
+    /// Comma-separated, with every column.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tab-separated, with every column.
+    pub fn tsv() -> Self {
+        Self::default().delimiter(b'\t')
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn columns(mut self, columns: Vec<Column>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Writes a header row followed by one row per entry. `windows` maps
+    /// tab ids to window ids.
+    pub fn write_entries<'a, W: Write>(
+        &self,
+        entries: impl IntoIterator<Item = &'a NavigationEntry>,
+        windows: &HashMap<i32, i32>,
+        out: W,
+    ) -> Result<(), SnssError> {
+        let mut writer = ::csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(out);
+        writer
+            .write_record(self.columns.iter().map(|column| column.name()))
+            .map_err(csv_error)?;
+        for entry in entries {
+            let window_id = windows.get(&entry.session_id()).copied();
+            writer
+                .write_record(
+                    self.columns
+                        .iter()
+                        .map(|column| column.value(entry, window_id)),
+                )
+                .map_err(csv_error)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Exports every navigation in a file. The whole file is read first,
+    /// so entries pick up their window even when it is assigned later.
+    pub fn write_file<W: Write>(&self, snss_file: &mut SnssFile, out: W) -> Result<(), SnssError> {
+        let mut entries = Vec::new();
+        let mut windows = HashMap::new();
+        for command in snss_file.iter_session_commands() {
+            match command? {
+                SessionCommand::NavigationEntry(entry) => entries.push(*entry),
+                SessionCommand::TabWindow(tab_window) => {
+                    windows.insert(tab_window.tab_id, tab_window.window_id);
+                }
+                _ => {}
+            }
+        }
+        self.write_entries(&entries, &windows, out)
+    }
+}
+
+fn csv_error(error: ::csv::Error) -> SnssError {
+    SnssError::IoError(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_names_round_trip() {
+        for column in Column::ALL {
+            assert_eq!(column.name().parse::<Column>(), Ok(column));
+        }
+        assert!("colour".parse::<Column>().is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! A self-contained HTML page showing a reconstructed session.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::TabGroupColor;
+use crate::session::{ClosedEntry, SessionState, Tab, Window};
+use crate::time::ChromeTime;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em; color: #202124; }
+h1 { font-size: 1.5em; }
+section.window { border: 1px solid #dadce0; border-radius: 8px; padding: 1em; margin-bottom: 1.5em; }
+section.window.closed, li.tab.closed { opacity: 0.6; }
+.meta { color: #5f6368; font-size: 0.9em; }
+ol.strip { list-style: none; padding: 0; display: flex; flex-wrap: wrap; gap: 4px; }
+ol.strip li { border: 1px solid #dadce0; border-top: 4px solid transparent; border-radius: 6px 6px 0 0;
+  padding: 4px 8px; max-width: 16em; overflow: hidden; white-space: nowrap; text-overflow: ellipsis; }
+ol.strip li.selected { background: #e8f0fe; }
+.group { color: #fff; border-radius: 4px; padding: 0 6px; font-size: 0.85em; }
+ol.history { font-size: 0.9em; }
+ol.history li.current { font-weight: bold; }
+time { color: #5f6368; font-variant-numeric: tabular-nums; margin-right: 0.5em; }
+";
+
+/// Renders a [`SessionState`] as one HTML file with inline styles and no
+/// scripts, so it can be mailed around and opened anywhere.
+#[derive(Debug, Clone)]
+pub struct HtmlReport {
+    title: String,
+}
+
+impl Default for HtmlReport {
+    fn default() -> Self {
+        Self {
+            title: "Browser session".to_string(),
+        }
+    }
+}
+
+impl HtmlReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Heading and page title, e.g. the name of the source file.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    pub fn write<W: Write>(&self, state: &SessionState, mut out: W) -> io::Result<()> {
+        out.write_all(self.render(state).as_bytes())
+    }
+
+    /// Returns the page as a string.
+    pub fn render(&self, state: &SessionState) -> String {
+        let mut html = String::new();
+        let title = escape(&self.title);
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+            title, STYLE, title
+        );
+
+        for window in state.windows() {
+            let tabs = state.tabs_in_window(window.id);
+            self.render_window(&mut html, state, window, &tabs);
+        }
+
+        let orphans: Vec<&Tab> = state
+            .tabs()
+            .filter(|tab| tab.window_id.is_none_or(|id| state.window(id).is_none()))
+            .collect();
+        if !orphans.is_empty() {
+            html.push_str("<section class=\"window\">\n<h2>Tabs without a window</h2>\n");
+            render_tabs(&mut html, state, &orphans);
+            html.push_str("</section>\n");
+        }
+
+        if !state.closed_entries().is_empty() {
+            html.push_str("<h2>Recently closed</h2>\n");
+            for entry in state.closed_entries() {
+                match entry {
+                    ClosedEntry::Tab(tab) => {
+                        html.push_str("<section class=\"window closed\">\n<h3>Tab</h3>\n");
+                        render_tabs(&mut html, state, &[tab]);
+                        html.push_str("</section>\n");
+                    }
+                    ClosedEntry::Window { window, tabs } => {
+                        let tabs: Vec<&Tab> = tabs.iter().collect();
+                        self.render_window(&mut html, state, window, &tabs);
+                    }
+                }
+            }
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn render_window(
+        &self,
+        html: &mut String,
+        state: &SessionState,
+        window: &Window,
+        tabs: &[&Tab],
+    ) {
+        let class = if window.closed.is_some() {
+            "window closed"
+        } else {
+            "window"
+        };
+        let _ = write!(
+            html,
+            "<section class=\"{}\">\n<h2>Window {}</h2>\n<p class=\"meta\">",
+            class, window.id
+        );
+        let mut meta = Vec::new();
+        if state.active_window_id() == Some(window.id) {
+            meta.push("active".to_string());
+        }
+        if let Some(bounds) = &window.bounds {
+            meta.push(format!(
+                "{}&times;{} at {},{} ({:?})",
+                bounds.width, bounds.height, bounds.x, bounds.y, bounds.show_state
+            ));
+        }
+        if let Some(closed) = window.closed {
+            meta.push(format!("closed {}", time(closed)));
+        }
+        html.push_str(&meta.join(" &middot; "));
+        html.push_str("</p>\n<ol class=\"strip\">\n");
+
+        let selected = window
+            .selected_tab_index
+            .and_then(|index| tabs.get(usize::try_from(index).ok()?))
+            .map(|tab| tab.id);
+        for tab in tabs {
+            let title = tab
+                .current_navigation()
+                .map(|navigation| navigation.entry.title())
+                .filter(|title| !title.is_empty())
+                .unwrap_or("(untitled)");
+            let class = if selected == Some(tab.id) {
+                " class=\"selected\""
+            } else {
+                ""
+            };
+            let border = tab
+                .group
+                .and_then(|group| state.group(group))
+                .map(|group| format!(" style=\"border-top-color: {}\"", group_color(group.color)))
+                .unwrap_or_default();
+            let pin = if tab.pinned { "&#128204; " } else { "" };
+            let _ = writeln!(
+                html,
+                "<li{}{}><a href=\"#tab-{}\">{}{}</a></li>",
+                class,
+                border,
+                tab.id,
+                pin,
+                escape(title)
+            );
+        }
+        html.push_str("</ol>\n");
+        render_tabs(html, state, tabs);
+        html.push_str("</section>\n");
+    }
+}
+
+fn render_tabs(html: &mut String, state: &SessionState, tabs: &[&Tab]) {
+    html.push_str("<ul class=\"tabs\">\n");
+    for tab in tabs {
+        let class = if tab.closed.is_some() {
+            "tab closed"
+        } else {
+            "tab"
+        };
+        let _ = write!(
+            html,
+            "<li class=\"{}\" id=\"tab-{}\">Tab {}",
+            class, tab.id, tab.id
+        );
+        if tab.pinned {
+            html.push_str(" &middot; pinned");
+        }
+        if let Some(group) = tab.group.and_then(|group| state.group(group)) {
+            let title = if group.title.is_empty() {
+                "(unnamed group)"
+            } else {
+                &group.title
+            };
+            let _ = write!(
+                html,
+                " &middot; <span class=\"group\" style=\"background: {}\">{}</span>",
+                group_color(group.color),
+                escape(title)
+            );
+        }
+        if let Some(closed) = tab.closed {
+            let _ = write!(html, " &middot; closed {}", time(closed));
+        }
+        html.push_str("\n<ol class=\"history\">\n");
+
+        let current = tab.current_navigation().map(|navigation| navigation.offset);
+        for navigation in &tab.navigations {
+            let entry = &navigation.entry;
+            let class = if current == Some(navigation.offset) {
+                " class=\"current\""
+            } else {
+                ""
+            };
+            let title = if entry.title().is_empty() {
+                entry.url()
+            } else {
+                entry.title()
+            };
+            let _ = writeln!(
+                html,
+                "<li{}>{}{}</li>",
+                class,
+                if entry.timestamp().is_null() {
+                    String::new()
+                } else {
+                    time(entry.timestamp())
+                },
+                link(entry.url(), title)
+            );
+        }
+        html.push_str("</ol>\n</li>\n");
+    }
+    html.push_str("</ul>\n");
+}
+
+fn time(time: ChromeTime) -> String {
+    format!("<time datetime=\"{0}\">{0}</time>", time)
+}
+
+/// A link for URLs a browser can open safely; anything else, such as
+/// `javascript:` URLs, is shown as text.
+fn link(url: &str, text: &str) -> String {
+    let scheme = url.split(':').next().unwrap_or_default();
+    if ["http", "https", "ftp", "file"]
+        .iter()
+        .any(|safe| scheme.eq_ignore_ascii_case(safe))
+    {
+        format!(
+            "<a href=\"{}\" title=\"{}\">{}</a>",
+            escape(url),
+            escape(url),
+            escape(text)
+        )
+    } else {
+        format!("<span title=\"{}\">{}</span>", escape(url), escape(text))
+    }
+}
+
+/// Chromium's light-mode colours for tab groups.
+fn group_color(color: TabGroupColor) -> &'static str {
+    match color {
+        TabGroupColor::Grey | TabGroupColor::Unknown(_) => "#5f6368",
+        TabGroupColor::Blue => "#1a73e8",
+        TabGroupColor::Red => "#d93025",
+        TabGroupColor::Yellow => "#f9ab00",
+        TabGroupColor::Green => "#1e8e3e",
+        TabGroupColor::Pink => "#d01884",
+        TabGroupColor::Purple => "#a142f4",
+        TabGroupColor::Cyan => "#007b83",
+        TabGroupColor::Orange => "#fa903e",
+    }
+}
+
+pub(super) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            escape(r#"<b>"Tom" & 'Jerry'</b>"#),
+            "&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn only_safe_schemes_are_links() {
+        assert!(link("https://example.com/", "x").starts_with("<a "));
+        assert!(link("JavaScript:alert(1)", "x").starts_with("<span "));
+    }
+}
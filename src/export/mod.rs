@@ -0,0 +1,15 @@
+//! Writers that turn session files into formats other tools can open.
+
+pub mod bookmarks;
+pub mod csv;
+pub mod html;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod timeline;
+
+pub use self::bookmarks::BookmarksExporter;
+pub use self::csv::{Column, CsvExporter};
+pub use self::html::HtmlReport;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteExporter;
+pub use self::timeline::{EventKind, Timeline, TimelineEvent};
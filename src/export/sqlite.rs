@@ -0,0 +1,251 @@
+//! Loads reconstructed sessions into SQLite.
+//!
+//! Every row is keyed by the source file it came from and the offset of
+//! the command behind it. Times are stored as Chromium's raw microseconds
+//! since 1601-01-01, the same representation as `visits.visit_time` in a
+//! `History` database, and `navigations.transition` holds the raw page
+//! transition like `visits.transition`, so the two can be joined directly.
+
+use std::path::Path;
+
+use rusqlite::{Connection, Transaction, params};
+
+use crate::session::{ClosedEntry, SessionState, Tab};
+use crate::time::ChromeTime;
+use crate::{SnssError, TabGroupId};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS windows (
+    source TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    window_id INTEGER NOT NULL,
+    window_type INTEGER,
+    selected_tab_index INTEGER,
+    x INTEGER,
+    y INTEGER,
+    width INTEGER,
+    height INTEGER,
+    show_state INTEGER,
+    is_active INTEGER NOT NULL,
+    closed_time INTEGER,
+    PRIMARY KEY (source, offset)
+);
+CREATE TABLE IF NOT EXISTS tabs (
+    source TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    tab_id INTEGER NOT NULL,
+    window_id INTEGER,
+    tab_index INTEGER,
+    guid TEXT,
+    pinned INTEGER NOT NULL,
+    group_id TEXT,
+    selected_navigation_index INTEGER,
+    last_active_time INTEGER,
+    closed_time INTEGER,
+    PRIMARY KEY (source, offset)
+);
+CREATE TABLE IF NOT EXISTS navigations (
+    source TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    tab_id INTEGER NOT NULL,
+    navigation_index INTEGER NOT NULL,
+    is_current INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    timestamp INTEGER,
+    transition INTEGER NOT NULL,
+    referrer_url TEXT,
+    original_request_url TEXT,
+    http_status INTEGER,
+    task_id INTEGER,
+    parent_task_id INTEGER,
+    root_task_id INTEGER,
+    PRIMARY KEY (source, offset)
+);
+CREATE TABLE IF NOT EXISTS groups (
+    source TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    group_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    color INTEGER NOT NULL,
+    PRIMARY KEY (source, offset)
+);
+CREATE TABLE IF NOT EXISTS closed_entries (
+    source TEXT NOT NULL,
+    offset INTEGER NOT NULL,
+    entry_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    closed_time INTEGER,
+    PRIMARY KEY (source, offset)
+);
+";
+
+/// Writes [`SessionState`]s into the `windows`, `tabs`, `navigations`,
+/// `groups` and `closed_entries` tables, creating them if needed.
+///
+/// Exporting the same source again replaces its rows.
+#[derive(Debug)]
+pub struct SqliteExporter {
+    connection: Connection,
+}
+
+impl SqliteExporter {
+    pub fn open(path: &Path) -> Result<Self, SnssError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn from_connection(connection: Connection) -> Result<Self, SnssError> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn into_connection(self) -> Connection {
+        self.connection
+    }
+
+    /// Writes one file's state in a single transaction. `source` names the
+    /// file, usually its path.
+    pub fn write_state(&mut self, source: &str, state: &SessionState) -> Result<(), SnssError> {
+        let transaction = self.connection.transaction()?;
+        for table in ["windows", "tabs", "navigations", "groups", "closed_entries"] {
+            transaction.execute(
+                &format!("DELETE FROM {} WHERE source = ?1", table),
+                params![source],
+            )?;
+        }
+
+        for window in state.windows() {
+            let bounds = window.bounds.as_ref();
+            transaction.execute(
+                "INSERT INTO windows VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    source,
+                    window.offset,
+                    window.id,
+                    window.window_type,
+                    window.selected_tab_index,
+                    bounds.map(|bounds| bounds.x),
+                    bounds.map(|bounds| bounds.y),
+                    bounds.map(|bounds| bounds.width),
+                    bounds.map(|bounds| bounds.height),
+                    bounds.map(|bounds| bounds.show_state.as_i32()),
+                    state.active_window_id() == Some(window.id),
+                    time(window.closed),
+                ],
+            )?;
+        }
+        for tab in state.tabs() {
+            write_tab(&transaction, source, tab)?;
+        }
+        for group in state.groups() {
+            transaction.execute(
+                "INSERT INTO groups VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    source,
+                    group.offset,
+                    group_id(Some(group.id)),
+                    group.title,
+                    group.color.as_u32(),
+                ],
+            )?;
+        }
+        for entry in state.closed_entries() {
+            let kind = match entry {
+                ClosedEntry::Tab(tab) => {
+                    write_tab(&transaction, source, tab)?;
+                    "tab"
+                }
+                ClosedEntry::Window { window, tabs } => {
+                    transaction.execute(
+                        "INSERT INTO windows (source, offset, window_id, selected_tab_index,
+                             is_active, closed_time)
+                         VALUES (?1, ?2, ?3, ?4, 0, ?5)",
+                        params![
+                            source,
+                            window.offset,
+                            window.id,
+                            window.selected_tab_index,
+                            time(window.closed),
+                        ],
+                    )?;
+                    for tab in tabs {
+                        write_tab(&transaction, source, tab)?;
+                    }
+                    "window"
+                }
+            };
+            transaction.execute(
+                "INSERT INTO closed_entries VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    source,
+                    entry.offset(),
+                    entry.id(),
+                    kind,
+                    time(entry.closed())
+                ],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+fn write_tab(transaction: &Transaction, source: &str, tab: &Tab) -> Result<(), SnssError> {
+    transaction.execute(
+        "INSERT INTO tabs VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            source,
+            tab.offset,
+            tab.id,
+            tab.window_id,
+            tab.index,
+            tab.guid,
+            tab.pinned,
+            group_id(tab.group),
+            tab.selected_navigation_index,
+            time(tab.last_active),
+            time(tab.closed),
+        ],
+    )?;
+
+    let current = tab.current_navigation().map(|navigation| navigation.offset);
+    for navigation in &tab.navigations {
+        let entry = &navigation.entry;
+        transaction.execute(
+            "INSERT INTO navigations VALUES
+                 (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+            params![
+                source,
+                navigation.offset,
+                tab.id,
+                entry.index(),
+                current == Some(navigation.offset),
+                entry.url(),
+                entry.title(),
+                time(Some(entry.timestamp())),
+                entry.transition().value(),
+                entry.referrer_url(),
+                entry.original_request_url(),
+                entry.http_status(),
+                entry.task_id(),
+                entry.parent_task_id(),
+                entry.root_task_id(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Raw microseconds, with Chromium's null time stored as NULL.
+fn time(time: Option<ChromeTime>) -> Option<i64> {
+    time.filter(|time| !time.is_null())
+        .map(ChromeTime::internal_value)
+}
+
+fn group_id(group: Option<TabGroupId>) -> Option<String> {
+    group.map(|group| group.to_string())
+}
@@ -0,0 +1,316 @@
+//! Timelines of every timestamp in session files, for forensic tools.
+//!
+//! Events can be written as a Sleuth Kit bodyfile for `mactime`, or as
+//! JSON lines shaped like the events of Plaso's `json_line` output, so they
+//! can be merged with other `log2timeline` results.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use serde_json::json;
+
+use crate::time::ChromeTime;
+use crate::{SessionCommand, SnssError, SnssFile, SnssFileType};
+
+/// What a timestamp records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    /// The creation time encoded in a `Session_`/`Tabs_` file name.
+    FileCreated,
+    /// A navigation committed.
+    Navigation,
+    /// A tab was last shown.
+    TabLastActive,
+    TabClosed,
+    WindowClosed,
+}
+
+impl EventKind {
+    /// Short name used in Plaso data types, e.g. `tab_closed`.
+    pub fn name(self) -> &'static str {
+        match self {
+            EventKind::FileCreated => "file_created",
+            EventKind::Navigation => "navigation",
+            EventKind::TabLastActive => "tab_last_active",
+            EventKind::TabClosed => "tab_closed",
+            EventKind::WindowClosed => "window_closed",
+        }
+    }
+
+    /// Plaso's `timestamp_desc`.
+    pub fn description(self) -> &'static str {
+        match self {
+            EventKind::FileCreated => "Creation Time",
+            EventKind::Navigation => "Last Visited Time",
+            EventKind::TabLastActive => "Last Access Time",
+            EventKind::TabClosed | EventKind::WindowClosed => "Closed Time",
+        }
+    }
+}
+
+/// One timestamped artefact.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    pub time: ChromeTime,
+    pub kind: EventKind,
+    /// The file the event came from, usually its path.
+    pub source: String,
+    pub file_type: SnssFileType,
+    /// Offset of the command holding the timestamp; `None` for events
+    /// taken from the file name.
+    pub offset: Option<u64>,
+    pub window_id: Option<i32>,
+    pub tab_id: Option<i32>,
+    /// For tab events, the page the tab showed.
+    pub url: Option<String>,
+    pub title: Option<String>,
+}
+
+impl TimelineEvent {
+    /// A one-line description, used as the bodyfile name and the Plaso
+    /// message.
+    pub fn message(&self) -> String {
+        let page = match (&self.url, &self.title) {
+            (Some(url), Some(title)) if !title.is_empty() => format!("{} ({})", url, title),
+            (Some(url), _) => url.clone(),
+            (None, _) => String::new(),
+        };
+        let tab = self
+            .tab_id
+            .map(|id| format!("Tab {}", id))
+            .unwrap_or_default();
+        match self.kind {
+            EventKind::FileCreated => format!("{} file created", self.file_type),
+            EventKind::Navigation => format!("{} navigated to {}", tab, page),
+            EventKind::TabLastActive => format!("{} last active: {}", tab, page),
+            EventKind::TabClosed => format!("{} closed: {}", tab, page),
+            EventKind::WindowClosed => {
+                format!("Window {} closed", self.window_id.unwrap_or_default())
+            }
+        }
+    }
+}
+
+/// Events collected from any number of files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeline {
+    events: Vec<TimelineEvent>,
+}
+
+/// Pages seen for a tab or closed entry, by navigation index.
+#[derive(Default)]
+struct TabPages {
+    pages: BTreeMap<i32, (String, String)>,
+    selected: Option<i32>,
+    window_id: Option<i32>,
+}
+
+impl TabPages {
+    fn current(&self) -> Option<&(String, String)> {
+        self.selected
+            .and_then(|index| self.pages.get(&index))
+            .or_else(|| self.pages.values().next_back())
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the events of one file. `source` names the file in the output.
+    ///
+    /// Tab events are labelled with the page the tab showed at the end of
+    /// the file, since the closing and last-active commands do not carry
+    /// one themselves.
+    pub fn add_file(&mut self, source: &str, snss_file: &mut SnssFile) -> Result<(), SnssError> {
+        let file_type = snss_file.file_type();
+        let event = |time, kind, offset| TimelineEvent {
+            time,
+            kind,
+            source: source.to_string(),
+            file_type,
+            offset,
+            window_id: None,
+            tab_id: None,
+            url: None,
+            title: None,
+        };
+
+        let mut found = Vec::new();
+        if let Some(created) = snss_file.created() {
+            let time = ChromeTime::from_system_time(created);
+            found.push(event(time, EventKind::FileCreated, None));
+        }
+
+        let mut tabs: HashMap<i32, TabPages> = HashMap::new();
+        for command in snss_file.iter_commands_with_offsets() {
+            let (offset, command) = command?;
+            let offset = Some(offset);
+            match command {
+                SessionCommand::NavigationEntry(entry) => {
+                    let (url, title) = (entry.url().to_string(), entry.title().to_string());
+                    tabs.entry(entry.session_id())
+                        .or_default()
+                        .pages
+                        .insert(entry.index(), (url.clone(), title.clone()));
+                    found.push(TimelineEvent {
+                        tab_id: Some(entry.session_id()),
+                        url: Some(url),
+                        title: Some(title),
+                        ..event(entry.timestamp(), EventKind::Navigation, offset)
+                    });
+                }
+                SessionCommand::TabWindow(tab_window) => {
+                    tabs.entry(tab_window.tab_id).or_default().window_id =
+                        Some(tab_window.window_id);
+                }
+                SessionCommand::SelectedNavigationIndex(payload) => {
+                    tabs.entry(payload.id).or_default().selected = Some(payload.index);
+                }
+                SessionCommand::LastActiveTime(payload) => found.push(TimelineEvent {
+                    tab_id: Some(payload.id),
+                    ..event(payload.time, EventKind::TabLastActive, offset)
+                }),
+                SessionCommand::TabClosed(payload) => found.push(TimelineEvent {
+                    tab_id: Some(payload.id),
+                    ..event(payload.time, EventKind::TabClosed, offset)
+                }),
+                SessionCommand::WindowClosed(payload) => found.push(TimelineEvent {
+                    window_id: Some(payload.id),
+                    ..event(payload.time, EventKind::WindowClosed, offset)
+                }),
+                SessionCommand::SelectedNavigationInTab(payload) => {
+                    tabs.entry(payload.id).or_default().selected = Some(payload.index);
+                    found.push(TimelineEvent {
+                        tab_id: Some(payload.id),
+                        ..event(payload.timestamp, EventKind::TabClosed, offset)
+                    });
+                }
+                SessionCommand::ClosedWindow(window) => found.push(TimelineEvent {
+                    window_id: Some(window.id),
+                    ..event(window.timestamp, EventKind::WindowClosed, offset)
+                }),
+                _ => {}
+            }
+        }
+
+        for mut event in found.into_iter().filter(|event| !event.time.is_null()) {
+            if let Some(tab) = event.tab_id.and_then(|id| tabs.get(&id)) {
+                event.window_id = event.window_id.or(tab.window_id);
+                if let (None, Some((url, title))) = (&event.url, tab.current()) {
+                    event.url = Some(url.clone());
+                    event.title = Some(title.clone());
+                }
+            }
+            self.events.push(event);
+        }
+        Ok(())
+    }
+
+    /// Events ordered by time, then by source and offset.
+    pub fn events(&self) -> Vec<&TimelineEvent> {
+        let mut events: Vec<&TimelineEvent> = self.events.iter().collect();
+        events.sort_by(|a, b| (a.time, &a.source, a.offset).cmp(&(b.time, &b.source, b.offset)));
+        events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Writes a bodyfile in the Sleuth Kit 3.x format:
+    /// `MD5|name|inode|mode|UID|GID|size|atime|mtime|ctime|crtime`.
+    ///
+    /// The name holds the source, offset and message. File creation goes
+    /// into `crtime`, navigations and last-active times into `atime`, and
+    /// closing times into `mtime`; the other times are zero, which
+    /// `mactime` ignores.
+    pub fn write_bodyfile<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for event in self.events() {
+            let seconds = event.time.unix_micros().div_euclid(1_000_000).max(0);
+            let (atime, mtime, crtime) = match event.kind {
+                EventKind::FileCreated => (0, 0, seconds),
+                EventKind::Navigation | EventKind::TabLastActive => (seconds, 0, 0),
+                EventKind::TabClosed | EventKind::WindowClosed => (0, seconds, 0),
+            };
+            let location = match event.offset {
+                Some(offset) => format!("{}@{}", event.source, offset),
+                None => event.source.clone(),
+            };
+            writeln!(
+                out,
+                "0|{}|0|0|0|0|0|{}|{}|0|{}",
+                bodyfile_escape(&format!(
+                    "[Chrome {}] {}: {}",
+                    event.file_type,
+                    location,
+                    event.message()
+                )),
+                atime,
+                mtime,
+                crtime
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes one JSON object per line with the attributes of a Plaso
+    /// event: `timestamp` in microseconds since the Unix epoch,
+    /// `timestamp_desc`, `data_type`, `parser`, `filename`, `message`, and
+    /// a WebKit `date_time`, plus the offset, ids, URL and title.
+    pub fn write_jsonl<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for event in self.events() {
+            let prefix = match event.file_type {
+                SnssFileType::Session => "chrome:session",
+                SnssFileType::Tab => "chrome:tab_restore",
+            };
+            let value = json!({
+                "__container_type__": "event",
+                "__type__": "AttributeContainer",
+                "data_type": format!("{}:{}", prefix, event.kind.name()),
+                "date_time": {
+                    "__class_name__": "WebKitTime",
+                    "__type__": "DateTimeValues",
+                    "timestamp": event.time.internal_value(),
+                },
+                "display_name": format!("OS:{}", event.source),
+                "filename": event.source,
+                "message": event.message(),
+                "parser": "chrome_snss",
+                "timestamp": event.time.unix_micros(),
+                "timestamp_desc": event.kind.description(),
+                "offset": event.offset,
+                "window_id": event.window_id,
+                "tab_id": event.tab_id,
+                "url": event.url,
+                "title": event.title,
+            });
+            serde_json::to_writer(&mut out, &value).map_err(io::Error::from)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// `mactime` splits on `|` and reads one event per line.
+fn bodyfile_escape(text: &str) -> String {
+    text.replace('|', "%7C").replace(['\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodyfile_names_stay_on_one_field() {
+        assert_eq!(
+            bodyfile_escape("https://a.example/?q=x|y\nz"),
+            "https://a.example/?q=x%7Cy z"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Typed decoding of a navigation's extended info map.
+//!
+//! Chromium lets components attach their own data to a navigation through
+//! `SerializedNavigationDriver`; it is stored as string key/value pairs
+//! whose values are numbers, base64 text or whole pickles. An
+//! [`ExtendedInfoRegistry`] maps the keys to decoders so the values can be
+//! inspected as something better than opaque strings.
+
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+use crate::iterator::{PickleError, PickleIterator};
+
+/// `SerializedNavigationEntry::PasswordState`, stored under the
+/// `password_state` key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordState {
+    Unknown,
+    NoPasswordField,
+    HasPasswordField,
+    Other(i64),
+}
+
+impl PasswordState {
+    pub fn from_i64(value: i64) -> Self {
+        match value {
+            0 => PasswordState::Unknown,
+            1 => PasswordState::NoPasswordField,
+            2 => PasswordState::HasPasswordField,
+            other => PasswordState::Other(other),
+        }
+    }
+}
+
+/// A decoded extended info value.
+#[derive(Debug, Clone)]
+pub enum ExtendedValue {
+    PasswordState(PasswordState),
+    /// Base64 text that did not hold a pickle.
+    Binary(Vec<u8>),
+    /// A base64-encoded `base::Pickle`, header included; see
+    /// [`ExtendedValue::pickle`].
+    Pickle(Vec<u8>),
+    /// A value with no decoder, or one its decoder rejected.
+    Text(String),
+    /// The result of a decoder registered by the caller; see
+    /// [`ExtendedValue::custom`] and [`ExtendedValue::downcast_ref`].
+    Custom(Arc<dyn CustomValue>),
+}
+
+/// A value a caller's decoder produced, which can be printed and taken
+/// back as its own type. Implemented for every suitable type.
+pub trait CustomValue: Any + fmt::Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + fmt::Debug + Send + Sync> CustomValue for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl ExtendedValue {
+    /// Wraps the result of a caller's decoder.
+    pub fn custom<T: CustomValue>(value: T) -> Self {
+        ExtendedValue::Custom(Arc::new(value))
+    }
+
+    /// The value of a caller's decoder as the type it produced; `None` for
+    /// other values and other types.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match self {
+            ExtendedValue::Custom(value) => value.as_ref().as_any().downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// Starts reading a [`ExtendedValue::Pickle`]; `None` for other values.
+    pub fn pickle(&self) -> Option<Result<PickleIterator, PickleError>> {
+        match self {
+            ExtendedValue::Pickle(data) => Some(PickleIterator::new(data.clone(), 4)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ExtendedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtendedValue::PasswordState(state) => write!(f, "{:?}", state),
+            ExtendedValue::Binary(data) => write!(f, "{} bytes", data.len()),
+            ExtendedValue::Pickle(data) => write!(f, "pickle of {} bytes", data.len()),
+            ExtendedValue::Text(text) => f.write_str(text),
+            ExtendedValue::Custom(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Decodes the raw value stored under a key; `None` if it does not have
+/// the expected form.
+pub type Decoder = Arc<dyn Fn(&str) -> Option<ExtendedValue> + Send + Sync>;
+
+pub fn decode_password_state(value: &str) -> Option<ExtendedValue> {
+    let value = value.trim().parse().ok()?;
+    Some(ExtendedValue::PasswordState(PasswordState::from_i64(value)))
+}
+
+/// Decodes base64 text, as a [`ExtendedValue::Pickle`] when the bytes
+/// carry a consistent pickle header and as [`ExtendedValue::Binary`]
+/// otherwise.
+pub fn decode_base64(value: &str) -> Option<ExtendedValue> {
+    let data = STANDARD.decode(value.trim()).ok()?;
+    if PickleIterator::new(data.clone(), 4).is_ok() {
+        Some(ExtendedValue::Pickle(data))
+    } else {
+        Some(ExtendedValue::Binary(data))
+    }
+}
+
+/// Decoders for extended info keys.
+///
+/// [`ExtendedInfoRegistry::default`] knows the keys Chromium itself writes;
+/// [`ExtendedInfoRegistry::register`] adds or replaces one. Values under
+/// keys without a decoder are kept as text, unless they are base64 that
+/// holds a pickle.
+#[derive(Clone)]
+pub struct ExtendedInfoRegistry {
+    decoders: HashMap<String, Decoder>,
+}
+
+impl ExtendedInfoRegistry {
+    /// A registry without any decoders.
+    pub fn empty() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+
+    pub fn register<F>(&mut self, key: impl Into<String>, decoder: F) -> &mut Self
+    where
+        F: Fn(&str) -> Option<ExtendedValue> + Send + Sync + 'static,
+    {
+        self.decoders.insert(key.into(), Arc::new(decoder));
+        self
+    }
+
+    pub fn is_registered(&self, key: &str) -> bool {
+        self.decoders.contains_key(key)
+    }
+
+    pub fn decode(&self, key: &str, value: &str) -> ExtendedValue {
+        let decoded = match self.decoders.get(key) {
+            Some(decoder) => decoder(value),
+            None => match decode_base64(value) {
+                pickle @ Some(ExtendedValue::Pickle(_)) => pickle,
+                _ => None,
+            },
+        };
+        decoded.unwrap_or_else(|| ExtendedValue::Text(value.to_string()))
+    }
+
+    /// Decodes every entry of a map, in key order.
+    pub fn decode_map<'a>(
+        &self,
+        map: &'a HashMap<String, String>,
+    ) -> BTreeMap<&'a str, ExtendedValue> {
+        map.iter()
+            .map(|(key, value)| (key.as_str(), self.decode(key, value)))
+            .collect()
+    }
+}
+
+impl Default for ExtendedInfoRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register("password_state", decode_password_state);
+        registry
+    }
+}
+
+impl fmt::Debug for ExtendedInfoRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut keys: Vec<_> = self.decoders.keys().collect();
+        keys.sort();
+        f.debug_struct("ExtendedInfoRegistry")
+            .field("keys", &keys)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pickle::PickleWriter;
+
+    #[test]
+    fn password_state() {
+        let registry = ExtendedInfoRegistry::default();
+        assert!(matches!(
+            registry.decode("password_state", "2"),
+            ExtendedValue::PasswordState(PasswordState::HasPasswordField)
+        ));
+        assert!(matches!(
+            registry.decode("password_state", "yes"),
+            ExtendedValue::Text(text) if text == "yes"
+        ));
+    }
+
+    #[test]
+    fn unregistered_pickle() {
+        let mut writer = PickleWriter::new();
+        writer.write_int32(7);
+        let encoded = STANDARD.encode(writer.into_bytes());
+
+        let registry = ExtendedInfoRegistry::default();
+        let value = registry.decode("some_component", &encoded);
+        let mut pickle = value.pickle().unwrap().unwrap();
+        assert_eq!(pickle.read_int32().unwrap(), 7);
+
+        // Plain words are valid base64 too, but not a pickle.
+        assert!(matches!(
+            registry.decode("some_component", "abcd"),
+            ExtendedValue::Text(_)
+        ));
+    }
+
+    #[test]
+    fn custom_decoder() {
+        #[derive(Debug, PartialEq)]
+        struct Flags(Vec<String>);
+
+        let mut registry = ExtendedInfoRegistry::empty();
+        registry.register("flags", |value| {
+            let flags = value.split(',').map(str::to_string).collect();
+            Some(ExtendedValue::custom(Flags(flags)))
+        });
+
+        let value = registry.decode("flags", "a,b");
+        assert_eq!(value.to_string(), r#"Flags(["a", "b"])"#);
+        assert_eq!(
+            value.downcast_ref::<Flags>(),
+            Some(&Flags(vec!["a".to_string(), "b".to_string()]))
+        );
+        assert_eq!(value.downcast_ref::<String>(), None);
+        assert_eq!(
+            ExtendedValue::Text("a,b".to_string()).downcast_ref::<Flags>(),
+            None
+        );
+        assert!(matches!(
+            registry.decode("password_state", "1"),
+            ExtendedValue::Text(_)
+        ));
+    }
+}
@@ -0,0 +1,737 @@
+//! Firefox's session store, so sessions can move between Chromium and
+//! Firefox in either direction.
+//!
+//! Firefox keeps its session in `sessionstore.jsonlz4` in the profile
+//! directory, and while running in `sessionstore-backups/recovery.jsonlz4`.
+//! Both are JSON compressed as a single LZ4 block behind Mozilla's own
+//! `mozLz40\0` header and the uncompressed length.
+
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::session::{ClosedEntry, SessionState, write_session_commands};
+use crate::time::ChromeTime;
+use crate::{
+    IdAndIndex, IdAndTime, NavigationEntry, PinnedState, ReferrerPolicy, SessionCommand, SnssError,
+    SnssFileType, TabGroupColor, TabGroupId, TabGroupMembership, TabGroupMetadata, TabWindow,
+    WindowBounds, WindowShowState,
+};
+
+const MOZLZ4_MAGIC: &[u8; 8] = b"mozLz40\0";
+
+/// `SystemPrincipal` serialized the way Firefox stores triggering
+/// principals; used for every entry, as there is no page to attribute the
+/// navigation to.
+const SYSTEM_PRINCIPAL_BASE64: &str = "eyIzIjp7fX0=";
+
+/// The top-level object of a session store file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStore {
+    pub version: (String, u32),
+    #[serde(default)]
+    pub windows: Vec<Window>,
+    /// 1-based index into `windows`, or 0 if none is selected.
+    #[serde(default)]
+    pub selected_window: usize,
+    #[serde(rename = "_closedWindows", default)]
+    pub closed_windows: Vec<Window>,
+    #[serde(default)]
+    pub session: SessionInfo,
+    #[serde(default)]
+    pub global: Map<String, Value>,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self {
+            version: ("sessionrestore".to_string(), 1),
+            windows: Vec::new(),
+            selected_window: 0,
+            closed_windows: Vec::new(),
+            session: SessionInfo::default(),
+            global: Map::new(),
+        }
+    }
+}
+
+/// Times are milliseconds since the Unix epoch.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    #[serde(default)]
+    pub last_update: i64,
+    #[serde(default)]
+    pub start_time: i64,
+    #[serde(default)]
+    pub recent_crashes: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Window {
+    #[serde(default)]
+    pub tabs: Vec<Tab>,
+    /// 1-based index into `tabs`.
+    #[serde(default)]
+    pub selected: usize,
+    #[serde(rename = "_closedTabs", default)]
+    pub closed_tabs: Vec<ClosedTab>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<Group>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_x: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_y: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i32>,
+    /// `normal`, `maximized`, `minimized` or `fullscreen`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sizemode: Option<String>,
+    /// Set for entries of `_closedWindows`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tab {
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+    /// 1-based index into `entries` of the page shown, or 0 for the last.
+    #[serde(default)]
+    pub index: usize,
+    #[serde(default)]
+    pub last_accessed: i64,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    #[serde(default)]
+    pub attributes: Map<String, Value>,
+    #[serde(default)]
+    pub user_context_id: u32,
+}
+
+/// A back/forward list entry.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    /// The older way of storing the referrer, which Firefox still reads
+    /// when `referrerInfo` is absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referrer: Option<String>,
+    /// Firefox's `nsIReferrerInfo` policy number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referrer_policy: Option<u32>,
+    #[serde(
+        rename = "triggeringPrincipal_base64",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub triggering_principal_base64: Option<String>,
+    #[serde(default)]
+    pub persist: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosedTab {
+    pub state: Tab,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub closed_at: i64,
+    /// Position the tab had in its window.
+    #[serde(default)]
+    pub pos: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Group {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+impl SessionStore {
+    /// Converts the states of a session file and, optionally, the
+    /// tab-restore file next to it.
+    ///
+    /// Open windows and tabs are restored as they were. Closed windows and
+    /// closed tabs become Firefox's recently closed windows and tabs;
+    /// closed tabs that no open window can take become a closed window of
+    /// their own.
+    pub fn from_states(states: &[&SessionState]) -> Self {
+        let mut store = SessionStore::default();
+        let mut stray_tabs = Vec::new();
+        for state in states {
+            match state.file_type() {
+                SnssFileType::Session => store.add_session(state, &mut stray_tabs),
+                SnssFileType::Tab => store.add_closed_entries(state, &mut stray_tabs),
+            }
+        }
+
+        match store
+            .windows
+            .get_mut(store.selected_window.saturating_sub(1))
+        {
+            Some(window) => window.closed_tabs.append(&mut stray_tabs),
+            None if !stray_tabs.is_empty() => {
+                let closed_at = stray_tabs.iter().map(|tab| tab.closed_at).max();
+                let tabs: Vec<Tab> = stray_tabs.into_iter().map(|tab| tab.state).collect();
+                store.closed_windows.push(Window {
+                    selected: 1,
+                    tabs,
+                    closed_at,
+                    ..Window::default()
+                });
+            }
+            None => {}
+        }
+        // Firefox lists recently closed entries newest first.
+        for window in &mut store.windows {
+            window
+                .closed_tabs
+                .sort_by_key(|tab| std::cmp::Reverse(tab.closed_at));
+        }
+        store
+            .closed_windows
+            .sort_by_key(|window| std::cmp::Reverse(window.closed_at));
+
+        let times = store
+            .windows
+            .iter()
+            .chain(&store.closed_windows)
+            .flat_map(|window| &window.tabs)
+            .map(|tab| tab.last_accessed)
+            .filter(|time| *time != 0);
+        store.session.start_time = times.clone().min().unwrap_or_default();
+        store.session.last_update = times.max().unwrap_or_default();
+        store
+    }
+
+    fn add_session(&mut self, state: &SessionState, stray_tabs: &mut Vec<ClosedTab>) {
+        for window in state.windows() {
+            let mut converted = Window::default();
+            if let Some(bounds) = &window.bounds {
+                converted.screen_x = Some(bounds.x);
+                converted.screen_y = Some(bounds.y);
+                converted.width = Some(bounds.width);
+                converted.height = Some(bounds.height);
+                converted.sizemode = Some(sizemode(bounds.show_state).to_string());
+            }
+
+            for tab in state.tabs_in_window(window.id) {
+                let mut converted_tab = convert_tab(tab);
+                if let Some(group) = tab.group {
+                    converted_tab.group_id = Some(group.to_string());
+                    if !converted
+                        .groups
+                        .iter()
+                        .any(|known| known.id == group.to_string())
+                    {
+                        converted.groups.push(convert_group(state, group));
+                    }
+                }
+                match tab.closed {
+                    Some(closed) if window.closed.is_none() => converted
+                        .closed_tabs
+                        .push(closed_tab(converted_tab, tab, closed)),
+                    _ => {
+                        if window.selected_tab_index.is_some()
+                            && tab.index == window.selected_tab_index
+                        {
+                            converted.selected = converted.tabs.len() + 1;
+                        }
+                        converted.tabs.push(converted_tab);
+                    }
+                }
+            }
+            if converted.tabs.is_empty() {
+                // Firefox cannot restore a window without tabs.
+                stray_tabs.append(&mut converted.closed_tabs);
+                continue;
+            }
+            if converted.selected == 0 {
+                converted.selected = 1;
+            }
+
+            match window.closed {
+                Some(closed) => {
+                    converted.closed_at = Some(millis(closed));
+                    self.closed_windows.push(converted);
+                }
+                None => {
+                    self.windows.push(converted);
+                    if self.selected_window == 0 || state.active_window_id() == Some(window.id) {
+                        self.selected_window = self.windows.len();
+                    }
+                }
+            }
+        }
+
+        // Tabs whose window was never recorded.
+        for tab in state
+            .tabs()
+            .filter(|tab| tab.window_id.is_none_or(|id| state.window(id).is_none()))
+        {
+            let converted = convert_tab(tab);
+            let closed = tab.closed.or(tab.last_active).unwrap_or_default();
+            stray_tabs.push(closed_tab(converted, tab, closed));
+        }
+    }
+
+    fn add_closed_entries(&mut self, state: &SessionState, stray_tabs: &mut Vec<ClosedTab>) {
+        for entry in state.closed_entries() {
+            match entry {
+                ClosedEntry::Tab(tab) => {
+                    let closed = tab.closed.unwrap_or_default();
+                    stray_tabs.push(closed_tab(convert_tab(tab), tab, closed));
+                }
+                ClosedEntry::Window { window, tabs } => {
+                    let tabs: Vec<Tab> = tabs.iter().map(convert_tab).collect();
+                    if tabs.is_empty() {
+                        continue;
+                    }
+                    let selected = window
+                        .selected_tab_index
+                        .and_then(|index| usize::try_from(index).ok())
+                        .filter(|index| *index < tabs.len())
+                        .unwrap_or_default();
+                    self.closed_windows.push(Window {
+                        tabs,
+                        selected: selected + 1,
+                        closed_at: window.closed.map(millis),
+                        ..Window::default()
+                    });
+                }
+            }
+        }
+    }
+
+    /// Writes the store as JSON.
+    pub fn write_json<W: Write>(&self, out: W) -> io::Result<()> {
+        serde_json::to_writer(out, self).map_err(io::Error::from)
+    }
+
+    /// Writes the store as a `.jsonlz4` file.
+    pub fn write_mozlz4<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let json = serde_json::to_vec(self).map_err(io::Error::from)?;
+        out.write_all(&compress_mozlz4(&json))
+    }
+
+    /// Reads a `.jsonlz4` file.
+    pub fn from_mozlz4(data: &[u8]) -> Result<Self, SnssError> {
+        Self::from_json(&decompress_mozlz4(data)?)
+    }
+
+    /// Reads uncompressed JSON, such as the old `sessionstore.js`.
+    pub fn from_json(json: &[u8]) -> Result<Self, SnssError> {
+        Ok(serde_json::from_slice(json).map_err(io::Error::from)?)
+    }
+
+    /// The commands of a session file that opens the same windows and
+    /// tabs, in the order Chromium writes its own snapshot.
+    ///
+    /// Windows and tabs are numbered from 1. Tabs without history entries,
+    /// which Firefox keeps for tabs that never finished loading, are
+    /// skipped, as are recently closed windows and tabs, which Chromium
+    /// keeps in the tab-restore file rather than the session file.
+    /// Firefox does not record per-entry times or transitions, so entries
+    /// have a null timestamp and the `LINK` transition.
+    pub fn to_session_commands(&self) -> Vec<SessionCommand> {
+        let mut commands = Vec::new();
+        let mut next_id = 1;
+        let mut active_window = None;
+        for (window_position, window) in self.windows.iter().enumerate() {
+            let tabs: Vec<(usize, &Tab)> = window
+                .tabs
+                .iter()
+                .enumerate()
+                .filter(|(_, tab)| !tab.entries.is_empty())
+                .collect();
+            if tabs.is_empty() {
+                continue;
+            }
+            let window_id = next_id;
+            next_id += 1;
+            if window_position + 1 == self.selected_window || active_window.is_none() {
+                active_window = Some(window_id);
+            }
+
+            if let (Some(width), Some(height)) = (window.width, window.height) {
+                commands.push(SessionCommand::WindowBounds(WindowBounds {
+                    window_id,
+                    x: window.screen_x.unwrap_or_default(),
+                    y: window.screen_y.unwrap_or_default(),
+                    width,
+                    height,
+                    show_state: show_state(window.sizemode.as_deref()),
+                }));
+            }
+            commands.push(SessionCommand::WindowType(IdAndIndex {
+                id: window_id,
+                index: 0,
+            }));
+
+            for (ordinal, group) in window.groups.iter().enumerate() {
+                commands.push(SessionCommand::TabGroupMetadata(TabGroupMetadata {
+                    group: import_group_id(&group.id, window_id, ordinal),
+                    title: group.name.as_str().into(),
+                    color: import_group_color(&group.color),
+                }));
+            }
+
+            let mut selected_index = 0;
+            for (index, (position, tab)) in tabs.iter().enumerate() {
+                let tab_id = next_id;
+                next_id += 1;
+                if position + 1 == window.selected {
+                    selected_index = index;
+                }
+                let index = index as i32;
+
+                commands.push(SessionCommand::TabWindow(TabWindow { window_id, tab_id }));
+                if tab.last_accessed > 0 {
+                    commands.push(SessionCommand::LastActiveTime(IdAndTime {
+                        id: tab_id,
+                        time: ChromeTime::from_unix_micros(tab.last_accessed * 1000),
+                    }));
+                }
+                for (navigation_index, entry) in tab.entries.iter().enumerate() {
+                    commands.push(SessionCommand::NavigationEntry(Box::new(import_entry(
+                        tab_id,
+                        navigation_index as i32,
+                        entry,
+                    ))));
+                }
+                // Like Firefox, a missing index selects the last entry.
+                let selected_navigation = match tab.index {
+                    0 => tab.entries.len(),
+                    index => index.min(tab.entries.len()),
+                }
+                .saturating_sub(1);
+                commands.push(SessionCommand::SelectedNavigationIndex(IdAndIndex {
+                    id: tab_id,
+                    index: selected_navigation as i32,
+                }));
+                commands.push(SessionCommand::TabIndexInWindow(IdAndIndex {
+                    id: tab_id,
+                    index,
+                }));
+                if tab.pinned {
+                    commands.push(SessionCommand::PinnedState(PinnedState {
+                        tab_id,
+                        pinned: true,
+                    }));
+                }
+                let group = tab.group_id.as_ref().and_then(|group_id| {
+                    let ordinal = window
+                        .groups
+                        .iter()
+                        .position(|group| &group.id == group_id)?;
+                    Some(import_group_id(group_id, window_id, ordinal))
+                });
+                if group.is_some() {
+                    commands.push(SessionCommand::TabGroup(TabGroupMembership {
+                        tab_id,
+                        group,
+                    }));
+                }
+            }
+            commands.push(SessionCommand::SelectedTabInIndex(IdAndIndex {
+                id: window_id,
+                index: selected_index as i32,
+            }));
+        }
+        if let Some(window_id) = active_window {
+            commands.push(SessionCommand::ActiveWindow(window_id));
+        }
+        commands
+    }
+
+    /// Writes a version 3 session file holding
+    /// [`SessionStore::to_session_commands`], which Chromium picks up when
+    /// named `Session_<time>` in a profile's `Sessions` directory.
+    pub fn write_session<W: Write>(&self, out: W) -> Result<(), SnssError> {
+        write_session_commands(&self.to_session_commands(), out)
+    }
+}
+
+fn import_entry(tab_id: i32, index: i32, entry: &Entry) -> NavigationEntry {
+    let url = match entry.url.as_str() {
+        "about:newtab" | "about:home" => "chrome://newtab/",
+        url => url,
+    };
+    let mut navigation = NavigationEntry::new(tab_id, index, url).with_title(&entry.title);
+    if let Some(referrer) = entry.referrer.as_deref().filter(|url| !url.is_empty()) {
+        let policy = entry
+            .referrer_policy
+            .map_or(ReferrerPolicy::Default, import_referrer_policy);
+        navigation = navigation.with_referrer(referrer, policy);
+    }
+    navigation
+}
+
+/// Keeps ids this crate exported, which are Chromium's own; anything else
+/// gets a new id that is stable across runs.
+fn import_group_id(id: &str, window_id: i32, ordinal: usize) -> TabGroupId {
+    match u128::from_str_radix(id, 16) {
+        Ok(token) if id.len() == 32 => TabGroupId {
+            high: (token >> 64) as u64,
+            low: token as u64,
+        },
+        _ => TabGroupId {
+            high: window_id as u64,
+            low: ordinal as u64 + 1,
+        },
+    }
+}
+
+fn import_group_color(color: &str) -> TabGroupColor {
+    match color {
+        "blue" => TabGroupColor::Blue,
+        "red" => TabGroupColor::Red,
+        "yellow" => TabGroupColor::Yellow,
+        "green" => TabGroupColor::Green,
+        "pink" => TabGroupColor::Pink,
+        "purple" => TabGroupColor::Purple,
+        "cyan" => TabGroupColor::Cyan,
+        "orange" => TabGroupColor::Orange,
+        _ => TabGroupColor::Grey,
+    }
+}
+
+fn show_state(sizemode: Option<&str>) -> WindowShowState {
+    match sizemode {
+        Some("maximized") => WindowShowState::Maximized,
+        Some("minimized") => WindowShowState::Minimized,
+        Some("fullscreen") => WindowShowState::Fullscreen,
+        _ => WindowShowState::Normal,
+    }
+}
+
+fn import_referrer_policy(policy: u32) -> ReferrerPolicy {
+    match policy {
+        1 => ReferrerPolicy::NoReferrerWhenDowngrade,
+        2 => ReferrerPolicy::Never,
+        3 => ReferrerPolicy::Origin,
+        4 => ReferrerPolicy::OriginWhenCrossOrigin,
+        5 => ReferrerPolicy::Always,
+        6 => ReferrerPolicy::SameOrigin,
+        7 => ReferrerPolicy::StrictOrigin,
+        8 => ReferrerPolicy::StrictOriginWhenCrossOrigin,
+        _ => ReferrerPolicy::Default,
+    }
+}
+
+fn convert_tab(tab: &crate::session::Tab) -> Tab {
+    let entries: Vec<Entry> = tab
+        .navigations
+        .iter()
+        .map(|navigation| {
+            let entry = &navigation.entry;
+            Entry {
+                url: entry.url().to_string(),
+                title: entry.title().to_string(),
+                referrer: entry
+                    .referrer_url()
+                    .filter(|url| !url.is_empty())
+                    .map(str::to_string),
+                referrer_policy: entry.referrer_policy().and_then(referrer_policy),
+                triggering_principal_base64: Some(SYSTEM_PRINCIPAL_BASE64.to_string()),
+                persist: true,
+            }
+        })
+        .collect();
+    let index = tab
+        .current_navigation()
+        .and_then(|current| {
+            tab.navigations
+                .iter()
+                .position(|navigation| navigation.offset == current.offset)
+        })
+        .map_or(0, |position| position + 1);
+    let last_accessed = tab
+        .last_active
+        .or_else(|| {
+            tab.current_navigation()
+                .map(|navigation| navigation.entry.timestamp())
+        })
+        .map_or(0, millis);
+    Tab {
+        entries,
+        index,
+        last_accessed,
+        pinned: tab.pinned,
+        ..Tab::default()
+    }
+}
+
+fn closed_tab(state: Tab, tab: &crate::session::Tab, closed: ChromeTime) -> ClosedTab {
+    let title = state
+        .entries
+        .get(state.index.saturating_sub(1))
+        .map(|entry| entry.title.clone())
+        .unwrap_or_default();
+    ClosedTab {
+        state,
+        title,
+        closed_at: millis(closed),
+        pos: tab
+            .index
+            .and_then(|index| usize::try_from(index).ok())
+            .unwrap_or_default(),
+    }
+}
+
+fn convert_group(state: &SessionState, id: TabGroupId) -> Group {
+    let group = state.group(id);
+    Group {
+        id: id.to_string(),
+        name: group.map(|group| group.title.clone()).unwrap_or_default(),
+        color: group_color(group.map_or(TabGroupColor::Grey, |group| group.color)).to_string(),
+        collapsed: false,
+    }
+}
+
+fn group_color(color: TabGroupColor) -> &'static str {
+    match color {
+        TabGroupColor::Grey | TabGroupColor::Unknown(_) => "gray",
+        TabGroupColor::Blue => "blue",
+        TabGroupColor::Red => "red",
+        TabGroupColor::Yellow => "yellow",
+        TabGroupColor::Green => "green",
+        TabGroupColor::Pink => "pink",
+        TabGroupColor::Purple => "purple",
+        TabGroupColor::Cyan => "cyan",
+        TabGroupColor::Orange => "orange",
+    }
+}
+
+fn sizemode(show_state: WindowShowState) -> &'static str {
+    match show_state {
+        WindowShowState::Minimized => "minimized",
+        WindowShowState::Maximized => "maximized",
+        WindowShowState::Fullscreen => "fullscreen",
+        _ => "normal",
+    }
+}
+
+/// Firefox numbers referrer policies differently from Chromium.
+fn referrer_policy(policy: ReferrerPolicy) -> Option<u32> {
+    match policy {
+        ReferrerPolicy::Default => Some(0),
+        ReferrerPolicy::NoReferrerWhenDowngrade => Some(1),
+        ReferrerPolicy::Never => Some(2),
+        ReferrerPolicy::Origin => Some(3),
+        ReferrerPolicy::OriginWhenCrossOrigin => Some(4),
+        ReferrerPolicy::Always => Some(5),
+        ReferrerPolicy::SameOrigin => Some(6),
+        ReferrerPolicy::StrictOrigin => Some(7),
+        ReferrerPolicy::StrictOriginWhenCrossOrigin => Some(8),
+        ReferrerPolicy::Unknown(_) => None,
+    }
+}
+
+fn millis(time: ChromeTime) -> i64 {
+    if time.is_null() {
+        0
+    } else {
+        time.unix_micros().div_euclid(1000)
+    }
+}
+
+/// Frames `data` as a mozlz4 file.
+pub fn compress_mozlz4(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2 + 12);
+    out.extend_from_slice(MOZLZ4_MAGIC);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&lz4_flex::block::compress(data));
+    out
+}
+
+/// An LZ4 sequence can grow by at most about 255 times, since every byte
+/// of a match length extension adds 255 bytes of output.
+const MAX_LZ4_RATIO: usize = 255;
+
+/// Unpacks a mozlz4 file such as `recovery.jsonlz4`.
+pub fn decompress_mozlz4(data: &[u8]) -> Result<Vec<u8>, SnssError> {
+    let rest = data
+        .strip_prefix(MOZLZ4_MAGIC)
+        .ok_or(SnssError::InvalidMagic)?;
+    let (size, block) = rest
+        .split_first_chunk::<4>()
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    let size = u32::from_le_bytes(*size) as usize;
+    // The size is allocated up front, so a forged one must not be trusted
+    // beyond what the block could expand to.
+    if size > block.len().saturating_mul(MAX_LZ4_RATIO) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} compressed bytes cannot hold {} bytes",
+                block.len(),
+                size
+            ),
+        )
+        .into());
+    }
+    let json = lz4_flex::block::decompress(block, size)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mozlz4_round_trip() {
+        let json = br#"{"version":["sessionrestore",1],"windows":[]}"#.repeat(20);
+        let framed = compress_mozlz4(&json);
+        assert_eq!(&framed[..8], b"mozLz40\0");
+        assert_eq!(&framed[8..12], &(json.len() as u32).to_le_bytes());
+        assert_eq!(decompress_mozlz4(&framed).unwrap(), json);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(matches!(
+            decompress_mozlz4(b"SNSS\x01\0\0\0"),
+            Err(SnssError::InvalidMagic)
+        ));
+        assert!(decompress_mozlz4(b"mozLz40\0\x10").is_err());
+    }
+
+    #[test]
+    fn rejects_implausible_sizes() {
+        let mut framed = b"mozLz40\0".to_vec();
+        framed.extend_from_slice(&u32::MAX.to_le_bytes());
+        framed.push(0);
+        assert!(decompress_mozlz4(&framed).is_err());
+    }
+
+    #[test]
+    fn principal_is_system() {
+        use base64::Engine;
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(SYSTEM_PRINCIPAL_BASE64)
+            .unwrap();
+        assert_eq!(decoded, br#"{"3":{}}"#);
+    }
+}
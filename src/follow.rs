@@ -0,0 +1,381 @@
+//! Follows a session file while the browser appends to it.
+//!
+//! Chromium keeps the current `Session_` and `Tabs_` files open and
+//! appends a command for every change. A [`Follower`] remembers how far it
+//! has read and, each time it is polled, decodes the complete commands
+//! written since. A command still being written is left for the next
+//! poll. Files are polled rather than watched, which works the same on
+//! every platform and on network shares.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::{HEADER_LENGTH, SessionCommand, SnssError, SnssFileType, SnssVersion};
+use crate::{decode_command, parse_header};
+
+/// Something that happened to the followed file.
+#[derive(Debug)]
+pub enum FollowEvent {
+    /// A command was appended; `offset` is that of its length prefix.
+    Command {
+        offset: u64,
+        command: SessionCommand,
+    },
+    /// A complete command that could not be decoded.
+    Invalid { offset: u64, error: SnssError },
+    /// The file shrank, so it was rewritten; reading starts over from its
+    /// first command.
+    Restarted,
+}
+
+impl FollowEvent {
+    /// The event as a JSON object with an `event` field naming its kind.
+    /// Commands carry a `command` field with their snake_case name and
+    /// their decoded fields.
+    pub fn to_json(&self) -> Value {
+        match self {
+            FollowEvent::Command { offset, command } => {
+                let mut value = command_json(command);
+                value["event"] = json!("command");
+                value["offset"] = json!(offset);
+                value
+            }
+            FollowEvent::Invalid { offset, error } => json!({
+                "event": "invalid",
+                "offset": offset,
+                "error": error.to_string(),
+            }),
+            FollowEvent::Restarted => json!({ "event": "restarted" }),
+        }
+    }
+}
+
+/// Reads the commands appended to a file since the last poll.
+#[derive(Debug, Clone)]
+pub struct Follower {
+    path: PathBuf,
+    file_type: SnssFileType,
+    version: Option<SnssVersion>,
+    /// Offset of the first command not read yet.
+    offset: u64,
+}
+
+impl Follower {
+    /// Follows the file at `path` from its first command. The type is taken
+    /// from the file name, and is a session file otherwise.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file_type = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(SnssFileType::from_file_name)
+            .unwrap_or(SnssFileType::Session);
+        Self {
+            path,
+            file_type,
+            version: None,
+            offset: HEADER_LENGTH,
+        }
+    }
+
+    /// Overrides the type taken from the file name.
+    pub fn with_file_type(mut self, file_type: SnssFileType) -> Self {
+        self.file_type = file_type;
+        self
+    }
+
+    /// Resumes after a command read earlier, from the offset a previous
+    /// follower reported by [`Follower::offset`].
+    pub fn resume_at(mut self, offset: u64) -> Self {
+        self.offset = offset.max(HEADER_LENGTH);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Offset of the first command not read yet.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The file's version, once its header has been read.
+    pub fn version(&self) -> Option<SnssVersion> {
+        self.version
+    }
+
+    /// Skips the commands already in the file, so that only those appended
+    /// later are reported.
+    pub fn skip_existing(&mut self) -> Result<(), SnssError> {
+        self.poll().map(|_| ())
+    }
+
+    /// Reads the commands appended since the last poll. A file that does
+    /// not exist yet, or whose header is not complete, has no events.
+    pub fn poll(&mut self) -> Result<Vec<FollowEvent>, SnssError> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let length = file.metadata()?.len();
+        let mut events = Vec::new();
+        if length < self.offset {
+            self.version = None;
+            self.offset = HEADER_LENGTH;
+            events.push(FollowEvent::Restarted);
+        }
+        if length < HEADER_LENGTH {
+            return Ok(events);
+        }
+
+        let mut header = [0u8; HEADER_LENGTH as usize];
+        file.read_exact(&mut header)?;
+        let version = parse_header(&header)?;
+        self.version = Some(version);
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+
+        // Only complete commands are decoded, each on its own so that one
+        // that fails does not hide those after it.
+        let mut start = 0;
+        while let Some(prefix) = data.get(start..start + 2) {
+            let end = start + 2 + u16::from_le_bytes([prefix[0], prefix[1]]) as usize;
+            if end > data.len() {
+                break;
+            }
+            let offset = self.offset + start as u64;
+            let command = &data[start + 2..end];
+            events.push(
+                match decode_command(self.file_type, version, offset, command) {
+                    Ok(command) => FollowEvent::Command { offset, command },
+                    Err(error) => FollowEvent::Invalid { offset, error },
+                },
+            );
+            start = end;
+        }
+        self.offset += start as u64;
+        Ok(events)
+    }
+
+    /// Polls every `interval` until something happens.
+    pub fn wait(&mut self, interval: Duration) -> Result<Vec<FollowEvent>, SnssError> {
+        loop {
+            let events = self.poll()?;
+            if !events.is_empty() {
+                return Ok(events);
+            }
+            thread::sleep(interval);
+        }
+    }
+}
+
+fn command_json(command: &SessionCommand) -> Value {
+    match command {
+        SessionCommand::NavigationEntry(entry) => json!({
+            "command": "navigation_entry",
+            "tab_id": entry.session_id(),
+            "index": entry.index(),
+            "url": entry.url(),
+            "title": entry.title(),
+            "transition": entry.transition().to_string(),
+            "timestamp": (!entry.timestamp().is_null()).then(|| entry.timestamp().to_string()),
+            "http_status": entry.http_status(),
+        }),
+        SessionCommand::TabWindow(payload) => json!({
+            "command": "tab_window",
+            "window_id": payload.window_id,
+            "tab_id": payload.tab_id,
+        }),
+        SessionCommand::WindowBounds(bounds) => json!({
+            "command": "window_bounds",
+            "window_id": bounds.window_id,
+            "x": bounds.x,
+            "y": bounds.y,
+            "width": bounds.width,
+            "height": bounds.height,
+            "show_state": bounds.show_state.as_i32(),
+        }),
+        SessionCommand::TabIndexInWindow(payload) => json!({
+            "command": "tab_index_in_window",
+            "tab_id": payload.id,
+            "index": payload.index,
+        }),
+        SessionCommand::SelectedNavigationIndex(payload) => json!({
+            "command": "selected_navigation_index",
+            "tab_id": payload.id,
+            "index": payload.index,
+        }),
+        SessionCommand::SelectedTabInIndex(payload) => json!({
+            "command": "selected_tab_in_index",
+            "window_id": payload.id,
+            "index": payload.index,
+        }),
+        SessionCommand::WindowType(payload) => json!({
+            "command": "window_type",
+            "window_id": payload.id,
+            "window_type": payload.index,
+        }),
+        SessionCommand::TabNavigationPathPrunedFromBack(payload) => json!({
+            "command": "tab_navigation_path_pruned_from_back",
+            "tab_id": payload.id,
+            "count": payload.index,
+        }),
+        SessionCommand::TabNavigationPathPrunedFromFront(payload) => json!({
+            "command": "tab_navigation_path_pruned_from_front",
+            "tab_id": payload.id,
+            "count": payload.index,
+        }),
+        SessionCommand::TabNavigationPathPruned(payload) => json!({
+            "command": "tab_navigation_path_pruned",
+            "tab_id": payload.id,
+            "index": payload.index,
+            "count": payload.count,
+        }),
+        SessionCommand::PinnedState(payload) => json!({
+            "command": "pinned_state",
+            "tab_id": payload.tab_id,
+            "pinned": payload.pinned,
+        }),
+        SessionCommand::TabClosed(payload) => json!({
+            "command": "tab_closed",
+            "tab_id": payload.id,
+            "time": payload.time.to_string(),
+        }),
+        SessionCommand::WindowClosed(payload) => json!({
+            "command": "window_closed",
+            "window_id": payload.id,
+            "time": payload.time.to_string(),
+        }),
+        SessionCommand::ActiveWindow(id) => json!({
+            "command": "active_window",
+            "window_id": id,
+        }),
+        SessionCommand::LastActiveTime(payload) => json!({
+            "command": "last_active_time",
+            "tab_id": payload.id,
+            "time": payload.time.to_string(),
+        }),
+        SessionCommand::TabGroup(payload) => json!({
+            "command": "tab_group",
+            "tab_id": payload.tab_id,
+            "group": payload.group.map(|group| group.to_string()),
+        }),
+        SessionCommand::TabGroupMetadata(metadata) => json!({
+            "command": "tab_group_metadata",
+            "group": metadata.group.to_string(),
+            "title": metadata.title.to_string(),
+            "color": metadata.color.as_u32(),
+        }),
+        SessionCommand::TabGuid(payload) => json!({
+            "command": "tab_guid",
+            "tab_id": payload.tab_id,
+            "guid": payload.guid,
+        }),
+        SessionCommand::SelectedNavigationInTab(payload) => json!({
+            "command": "selected_navigation_in_tab",
+            "id": payload.id,
+            "index": payload.index,
+            "time": payload.timestamp.to_string(),
+        }),
+        SessionCommand::ClosedWindow(window) => json!({
+            "command": "closed_window",
+            "id": window.id,
+            "selected_tab_index": window.selected_tab_index,
+            "num_tabs": window.num_tabs,
+            "time": window.timestamp.to_string(),
+        }),
+        SessionCommand::EntryPinned(pinned) => json!({
+            "command": "entry_pinned",
+            "pinned": pinned,
+        }),
+        SessionCommand::RestoredEntry(id) => json!({
+            "command": "restored_entry",
+            "id": id,
+        }),
+        SessionCommand::InitialStateMarker => json!({ "command": "initial_state_marker" }),
+        SessionCommand::UnprocessedEntry(entry) => json!({
+            "command": "unprocessed",
+            "type": format!("{:?}", entry.command_type()),
+            "length": entry.length(),
+        }),
+        SessionCommand::EOF => json!({ "command": "eof" }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use crate::{IdAndIndex, SnssWriter, TabWindow};
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+
+    fn commands(events: &[FollowEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| {
+                event.to_json()["command"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_appended_commands() {
+        let dir = TempDir::new("follow");
+        let path = dir.path().join("Session_13345678901234567");
+
+        let mut follower = Follower::new(&path);
+        assert!(follower.poll().unwrap().is_empty());
+
+        let mut writer =
+            SnssWriter::new(SnssFileType::Session, SnssVersion::V3, Vec::new()).unwrap();
+        writer
+            .write_command(&SessionCommand::TabWindow(TabWindow {
+                window_id: 1,
+                tab_id: 2,
+            }))
+            .unwrap();
+        fs::write(&path, writer.into_inner()).unwrap();
+        let events = follower.poll().unwrap();
+        assert_eq!(commands(&events), ["tab_window"]);
+        assert!(matches!(events[0], FollowEvent::Command { offset: 8, .. }));
+        assert_eq!(follower.offset(), 19);
+
+        // A command written in two parts is only reported once complete.
+        let mut writer =
+            SnssWriter::new(SnssFileType::Session, SnssVersion::V3, Vec::new()).unwrap();
+        writer
+            .write_command(&SessionCommand::SelectedTabInIndex(IdAndIndex {
+                id: 1,
+                index: 0,
+            }))
+            .unwrap();
+        let command = writer.into_inner().split_off(8);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&command[..4]).unwrap();
+        assert!(follower.poll().unwrap().is_empty());
+        file.write_all(&command[4..]).unwrap();
+        let events = follower.poll().unwrap();
+        assert_eq!(commands(&events), ["selected_tab_in_index"]);
+        assert_eq!(events[0].to_json()["offset"], 19);
+
+        fs::write(&path, b"SNSS\x03\0\0\0").unwrap();
+        assert_eq!(
+            follower.poll().unwrap()[0].to_json()["event"],
+            json!("restarted")
+        );
+        assert_eq!(follower.offset(), 8);
+    }
+}
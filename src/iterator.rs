@@ -1,75 +1,166 @@
 extern crate serde;
 extern crate thiserror;
 
-use byteorder::{LittleEndian, ReadBytesExt};
-use std::io::{self, Cursor, Read, Seek, SeekFrom};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
+use std::io;
 use thiserror::Error;
 
+use crate::time::{ChromeTime, TimeTicks};
+
 #[derive(Error, Debug)]
 pub enum PickleError {
     #[error("Invalid pickle length")]
     InvalidPickleLength,
+    #[error("Read of {wanted} bytes at offset {offset} exceeds the {available} bytes available")]
+    OutOfBounds {
+        offset: usize,
+        wanted: usize,
+        available: usize,
+    },
     #[error("Invalid boolean value")]
     InvalidBool,
+    #[error("Invalid element count {0}")]
+    InvalidCount(i32),
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
     #[error("UTF-8 decoding error: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
-    #[error("UTF-16 conversion error")]
-    Utf16ConversionError,
     #[error("UTF-16 decoding error: {0}")]
     Utf16Error(#[from] std::string::FromUtf16Error),
 }
 
+/// A UTF-16 string as read from a pickle, together with its decoded form.
+///
+/// Chromium does not validate titles before saving them, so they may hold
+/// unpaired surrogates, e.g. from an emoji cut in half by truncation. The
+/// decoded text replaces those with U+FFFD; the code units are kept
+/// verbatim for anyone who needs the exact bytes.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct String16 {
+    units: Vec<u16>,
+    text: String,
+    lossy: bool,
+}
+
+impl String16 {
+    pub fn from_units(units: Vec<u16>) -> Self {
+        let (text, lossy) = match String::from_utf16(&units) {
+            Ok(text) => (text, false),
+            Err(_) => (String::from_utf16_lossy(&units), true),
+        };
+        Self { units, text, lossy }
+    }
+
+    pub fn units(&self) -> &[u16] {
+        &self.units
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Whether any code units had to be replaced while decoding.
+    pub fn is_lossy(&self) -> bool {
+        self.lossy
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+}
+
+impl From<&str> for String16 {
+    fn from(text: &str) -> Self {
+        Self {
+            units: text.encode_utf16().collect(),
+            text: text.to_string(),
+            lossy: false,
+        }
+    }
+}
+
+impl fmt::Debug for String16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.lossy {
+            write!(f, "{:?} (lossy, units {:04x?})", self.text, self.units)
+        } else {
+            write!(f, "{:?}", self.text)
+        }
+    }
+}
+
+impl fmt::Display for String16 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Size of the payload-length header at the start of every pickle.
+const HEADER_SIZE: usize = 4;
+
+/// Reads the fields of a Chromium `base::Pickle`.
+///
+/// Every read is checked against the bytes remaining in the payload before
+/// anything is allocated, so a corrupt length prefix fails with
+/// [`PickleError::OutOfBounds`] instead of a huge allocation or a panic.
+/// A read that would run past the end leaves the position unchanged.
 #[derive(Debug)]
 pub struct PickleIterator {
-    cursor: Cursor<Vec<u8>>,
+    data: Vec<u8>,
+    position: usize,
     alignment: usize,
 }
 
 impl PickleIterator {
     pub fn new(data: Vec<u8>, alignment: usize) -> Result<Self, PickleError> {
-        let mut cursor = Cursor::new(data);
-        let pickle_length = cursor.read_u32::<LittleEndian>()? as usize;
-
-        if cursor.get_ref().len() != pickle_length + 4 {
-            return Err(PickleError::InvalidPickleLength);
+        if data.len() < HEADER_SIZE {
+            return Err(PickleError::OutOfBounds {
+                offset: 0,
+                wanted: HEADER_SIZE,
+                available: data.len(),
+            });
         }
 
-        Ok(Self { cursor, alignment })
-    }
-
-    // TODO: just impl directly on Cursor<Vec<u8>> !
-    /*
-    pub fn new(mut cursor: &'a mut Cursor<Vec<u8>>) -> Result<Self, PickleError> {
-        println!("cursor");
-        /*let pickle_length = */
-        //cursor.read_u32::<LittleEndian>()?;
-
-        /*
-        if cursor.get_ref().len() != pickle_length + 4 {
+        let pickle_length = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        if data.len() - HEADER_SIZE != pickle_length {
             return Err(PickleError::InvalidPickleLength);
         }
-        */
 
         Ok(Self {
-            cursor,
-            alignment: 4,
+            data,
+            position: HEADER_SIZE,
+            alignment: alignment.max(1),
         })
     }
-     */
 
-    pub fn read_aligned(&mut self, length: usize) -> Result<Vec<u8>, PickleError> {
-        let mut buffer = vec![0u8; length];
-        self.cursor.read_exact(&mut buffer)?;
+    /// Offset of the next read from the start of the pickle, header
+    /// included.
+    pub fn offset(&self) -> usize {
+        self.position
+    }
+
+    /// Bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
 
-        let align_count = self.alignment - (length % self.alignment);
-        if align_count != self.alignment {
-            println!("seek {} {}", align_count, self.alignment);
-            self.cursor.seek(SeekFrom::Current(align_count as i64))?;
+    pub fn read_aligned(&mut self, length: usize) -> Result<Vec<u8>, PickleError> {
+        let available = self.remaining();
+        if length > available {
+            return Err(PickleError::OutOfBounds {
+                offset: self.position,
+                wanted: length,
+                available,
+            });
         }
 
+        let buffer = self.data[self.position..self.position + length].to_vec();
+
+        // Chromium pads every field to the alignment; the padding of the
+        // last field may be missing from a truncated payload.
+        let padded = length.div_ceil(self.alignment) * self.alignment;
+        self.position += padded.min(available);
+
         Ok(buffer)
     }
 
@@ -129,28 +220,51 @@ impl PickleIterator {
     }
 
     pub fn read_string(&mut self) -> Result<String, PickleError> {
+        let start = self.position;
         let length = self.read_uint32()? as usize;
-        let raw = self.read_aligned(length)?;
+        let raw = self
+            .read_aligned(length)
+            .inspect_err(|_| self.position = start)?;
         Ok(String::from_utf8(raw)?)
     }
 
-    pub fn read_string16(&mut self) -> Result<String, PickleError> {
-        let length = self.read_uint32()? as usize * 2;
-        let raw = self.read_aligned(length)?;
+    fn read_utf16_units(&mut self) -> Result<Vec<u16>, PickleError> {
+        let start = self.position;
+        let units = self.read_uint32()? as usize;
+        let raw = match units.checked_mul(2) {
+            Some(length) => self.read_aligned(length),
+            None => Err(PickleError::OutOfBounds {
+                offset: self.position,
+                wanted: usize::MAX,
+                available: self.remaining(),
+            }),
+        }
+        .inspect_err(|_| self.position = start)?;
 
-        let iter = (0..length).map(|i| u16::from_be_bytes([raw[2 * i], raw[2 * i + 1]]));
+        Ok(raw
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect())
+    }
+
+    /// Reads a `std::u16string`, failing on unpaired surrogates.
+    pub fn read_string16(&mut self) -> Result<String, PickleError> {
+        let units = self.read_utf16_units()?;
+        Ok(String::from_utf16(&units)?)
+    }
 
-        let decode = char::decode_utf16(iter)
-            .collect::<Result<String, _>>()
-            .unwrap();
+    /// Reads a `std::u16string`, replacing unpaired surrogates with
+    /// U+FFFD while keeping the original code units.
+    pub fn read_string16_lossy(&mut self) -> Result<String16, PickleError> {
+        Ok(String16::from_units(self.read_utf16_units()?))
+    }
 
-        Ok(decode)
+    pub fn read_datetime(&mut self) -> Result<ChromeTime, PickleError> {
+        Ok(ChromeTime::from_internal(self.read_int64()?))
     }
 
-    pub fn read_datetime(&mut self) -> Result<SystemTime, PickleError> {
-        let microseconds = self.read_uint64()?;
-        let duration = std::time::Duration::from_micros(microseconds);
-        Ok(UNIX_EPOCH + duration)
+    pub fn read_time_ticks(&mut self) -> Result<TimeTicks, PickleError> {
+        Ok(TimeTicks::from_internal(self.read_int64()?))
     }
 }
 
@@ -165,3 +279,83 @@ fn main() {
     println!("Read value: {}", value);
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string16_pickle(units: &[u16]) -> PickleIterator {
+        let mut body = (units.len() as u32).to_le_bytes().to_vec();
+        body.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+        body.resize(body.len().next_multiple_of(4), 0);
+
+        let mut data = (body.len() as u32).to_le_bytes().to_vec();
+        data.extend(body);
+        PickleIterator::new(data, 4).unwrap()
+    }
+
+    #[test]
+    fn string16_is_little_endian() {
+        let units: Vec<u16> = "Grüße 😀".encode_utf16().collect();
+        let mut pickle = string16_pickle(&units);
+        assert_eq!(pickle.read_string16().unwrap(), "Grüße 😀");
+    }
+
+    #[test]
+    fn string16_unpaired_surrogate() {
+        let units = [0x0061, 0xd83d, 0x0062];
+
+        let mut pickle = string16_pickle(&units);
+        assert!(matches!(
+            pickle.read_string16(),
+            Err(PickleError::Utf16Error(_))
+        ));
+
+        let mut pickle = string16_pickle(&units);
+        let lossy = pickle.read_string16_lossy().unwrap();
+        assert!(lossy.is_lossy());
+        assert_eq!(lossy.as_str(), "a\u{fffd}b");
+        assert_eq!(lossy.units(), units);
+    }
+
+    #[test]
+    fn oversized_length_prefix_is_out_of_bounds() {
+        let mut data = 8u32.to_le_bytes().to_vec();
+        data.extend(u32::MAX.to_le_bytes());
+        data.extend(b"abcd");
+        let mut pickle = PickleIterator::new(data, 4).unwrap();
+
+        assert!(matches!(
+            pickle.read_string(),
+            Err(PickleError::OutOfBounds {
+                offset: 8,
+                wanted: 0xffff_ffff,
+                available: 4,
+            })
+        ));
+        assert_eq!(pickle.offset(), 4);
+        assert_eq!(pickle.read_uint32().unwrap(), u32::MAX);
+    }
+
+    #[test]
+    fn read_past_end() {
+        let mut data = 4u32.to_le_bytes().to_vec();
+        data.extend(7i32.to_le_bytes());
+        let mut pickle = PickleIterator::new(data, 4).unwrap();
+
+        assert_eq!(pickle.read_int32().unwrap(), 7);
+        assert_eq!(pickle.remaining(), 0);
+        assert!(matches!(
+            pickle.read_int64(),
+            Err(PickleError::OutOfBounds { wanted: 8, .. })
+        ));
+    }
+
+    #[test]
+    fn short_header() {
+        assert!(matches!(
+            PickleIterator::new(vec![1, 0], 4),
+            Err(PickleError::OutOfBounds { wanted: 4, .. })
+        ));
+    }
+}
@@ -0,0 +1,1449 @@
+extern crate bitflags;
+extern crate byteorder;
+extern crate thiserror;
+
+// Lets the pickle derives refer to `::snss` from inside this crate too.
+extern crate self as snss;
+
+mod detect;
+pub mod diff;
+pub mod discovery;
+pub mod export;
+pub mod extended_info;
+pub mod firefox;
+pub mod follow;
+pub mod iterator;
+pub mod merge;
+mod pickle;
+pub mod session;
+pub mod tasks;
+#[cfg(test)]
+mod testing;
+pub mod time;
+mod transition;
+pub mod writer;
+
+use bitflags::bitflags;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use std::time::SystemTime;
+use thiserror::Error;
+
+pub use detect::Detection;
+pub use extended_info::{ExtendedInfoRegistry, ExtendedValue};
+pub use iterator::{PickleError, PickleIterator, String16};
+pub use pickle::{FromPickle, PickleWriter, ToPickle};
+pub use snss_derive::{FromPickle, ToPickle};
+pub use time::{ChromeTime, TimeTicks};
+pub use transition::{CoreTransition, PageTransition, ParseTransitionError, Qualifier};
+pub use writer::SnssWriter;
+
+#[derive(Error, Debug)]
+pub enum SnssError {
+    #[error("Invalid magic number")]
+    InvalidMagic,
+    #[error("Unsupported version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+    #[error("Pickle error: {0}")]
+    PickleError(#[from] PickleError),
+    #[error("Invalid command type")]
+    InvalidCommandType,
+    #[error("Empty command at offset {0}")]
+    EmptyCommand(u64),
+    #[error("Unprocessed entry: {0} {1}")]
+    UnprocessedEntry(SnssFileType, u8),
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnssFileType {
+    Session,
+    Tab,
+}
+
+impl SnssFileType {
+    /// Infers the type from Chromium's file naming: `Session_<time>` and
+    /// `Tabs_<time>`, or the legacy `Current Session`, `Last Session`,
+    /// `Current Tabs` and `Last Tabs`.
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        match name {
+            "Current Session" | "Last Session" => Some(SnssFileType::Session),
+            "Current Tabs" | "Last Tabs" => Some(SnssFileType::Tab),
+            _ if name.starts_with("Session_") => Some(SnssFileType::Session),
+            _ if name.starts_with("Tabs_") => Some(SnssFileType::Tab),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the raw timestamp in a `Session_<timestamp>` or `Tabs_<timestamp>`
+/// file name: microseconds since 1601-01-01 UTC.
+pub fn file_name_timestamp_micros(name: &str) -> Option<u64> {
+    name.strip_prefix("Session_")
+        .or_else(|| name.strip_prefix("Tabs_"))?
+        .parse()
+        .ok()
+}
+
+/// Returns the creation time Chromium encodes in `Session_<timestamp>` and
+/// `Tabs_<timestamp>` file names.
+pub fn file_name_timestamp(name: &str) -> Option<SystemTime> {
+    let micros = i64::try_from(file_name_timestamp_micros(name)?).ok()?;
+    Some(ChromeTime::from_internal(micros).to_system_time())
+}
+
+impl fmt::Display for SnssFileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnssFileType::Session => write!(f, "Session"),
+            SnssFileType::Tab => write!(f, "Tab"),
+        }
+    }
+}
+
+/// On-disk format version from the SNSS header.
+///
+/// Chromium writes version 1 for plain files and version 3 once it started
+/// emitting the initial-state marker. Versions 2 and 4 are the encrypted
+/// variants of those and are rejected with [`SnssError::UnsupportedVersion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnssVersion {
+    V1,
+    V3,
+}
+
+impl SnssVersion {
+    pub fn from_u32(value: u32) -> Result<Self, SnssError> {
+        match value {
+            1 => Ok(SnssVersion::V1),
+            3 => Ok(SnssVersion::V3),
+            other => Err(SnssError::UnsupportedVersion(other)),
+        }
+    }
+
+    pub fn as_u32(self) -> u32 {
+        match self {
+            SnssVersion::V1 => 1,
+            SnssVersion::V3 => 3,
+        }
+    }
+
+    /// Whether command id 255 is the initial-state marker rather than an
+    /// unused command.
+    pub fn has_marker(self) -> bool {
+        self == SnssVersion::V3
+    }
+}
+
+impl fmt::Display for SnssVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_u32())
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionRestoreIdType {
+    CommandSetTabWindow = 0,
+    CommandSetWindowBounds = 1,
+    CommandSetTabIndexInWindow = 2,
+    CommandTabNavigationPathPrunedFromBack = 5,
+    CommandUpdateTabNavigation = 6,
+    CommandSetSelectedNavigationIndex = 7,
+    CommandSetSelectedTabInIndex = 8,
+    CommandSetWindowType = 9,
+    CommandSetWindowBounds2 = 10,
+    CommandTabNavigationPathPrunedFromFront = 11,
+    CommandSetPinnedState = 12,
+    CommandSetExtensionAppID = 13,
+    CommandSetWindowBounds3 = 14,
+    CommandSetWindowAppName = 15,
+    CommandTabClosed = 16,
+    CommandWindowClosed = 17,
+    CommandSetTabUserAgentOverride = 18,
+    CommandSessionStorageAssociated = 19,
+    CommandSetActiveWindow = 20,
+    CommandLastActiveTime = 21,
+    CommandSetWindowWorkspace = 22,
+    CommandSetWindowWorkspace2 = 23,
+    CommandTabNavigationPathPruned = 24,
+    CommandSetTabGroup = 25,
+    CommandSetTabGroupMetadata = 26,
+    CommandSetTabGroupMetadata2 = 27,
+    CommandSetTabGuid = 28,
+    CommandSetTabUserAgentOverride2 = 29,
+    CommandSetTabData = 30,
+    CommandSetWindowUserTitle = 31,
+    CommandSetWindowVisibleOnAllWorkspaces = 32,
+    CommandAddTabExtraData = 33,
+    CommandAddWindowExtraData = 34,
+    EdgeCommandUnknown131 = 131,
+    EdgeCommandUnknown132 = 132,
+    UnusedCommand = 255,
+    Unknown(u8) = 254,
+}
+
+impl SessionRestoreIdType {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => SessionRestoreIdType::CommandSetTabWindow,
+            1 => SessionRestoreIdType::CommandSetWindowBounds,
+            2 => SessionRestoreIdType::CommandSetTabIndexInWindow,
+            5 => SessionRestoreIdType::CommandTabNavigationPathPrunedFromBack,
+            6 => SessionRestoreIdType::CommandUpdateTabNavigation,
+            7 => SessionRestoreIdType::CommandSetSelectedNavigationIndex,
+            8 => SessionRestoreIdType::CommandSetSelectedTabInIndex,
+            9 => SessionRestoreIdType::CommandSetWindowType,
+            10 => SessionRestoreIdType::CommandSetWindowBounds2,
+            11 => SessionRestoreIdType::CommandTabNavigationPathPrunedFromFront,
+            12 => SessionRestoreIdType::CommandSetPinnedState,
+            13 => SessionRestoreIdType::CommandSetExtensionAppID,
+            14 => SessionRestoreIdType::CommandSetWindowBounds3,
+            15 => SessionRestoreIdType::CommandSetWindowAppName,
+            16 => SessionRestoreIdType::CommandTabClosed,
+            17 => SessionRestoreIdType::CommandWindowClosed,
+            18 => SessionRestoreIdType::CommandSetTabUserAgentOverride,
+            19 => SessionRestoreIdType::CommandSessionStorageAssociated,
+            20 => SessionRestoreIdType::CommandSetActiveWindow,
+            21 => SessionRestoreIdType::CommandLastActiveTime,
+            22 => SessionRestoreIdType::CommandSetWindowWorkspace,
+            23 => SessionRestoreIdType::CommandSetWindowWorkspace2,
+            24 => SessionRestoreIdType::CommandTabNavigationPathPruned,
+            25 => SessionRestoreIdType::CommandSetTabGroup,
+            26 => SessionRestoreIdType::CommandSetTabGroupMetadata,
+            27 => SessionRestoreIdType::CommandSetTabGroupMetadata2,
+            28 => SessionRestoreIdType::CommandSetTabGuid,
+            29 => SessionRestoreIdType::CommandSetTabUserAgentOverride2,
+            30 => SessionRestoreIdType::CommandSetTabData,
+            31 => SessionRestoreIdType::CommandSetWindowUserTitle,
+            32 => SessionRestoreIdType::CommandSetWindowVisibleOnAllWorkspaces,
+            33 => SessionRestoreIdType::CommandAddTabExtraData,
+            34 => SessionRestoreIdType::CommandAddWindowExtraData,
+            131 => SessionRestoreIdType::EdgeCommandUnknown131,
+            132 => SessionRestoreIdType::EdgeCommandUnknown132,
+            255 => SessionRestoreIdType::UnusedCommand,
+            unknown => SessionRestoreIdType::Unknown(unknown),
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            SessionRestoreIdType::CommandSetTabWindow => 0,
+            SessionRestoreIdType::CommandSetWindowBounds => 1,
+            SessionRestoreIdType::CommandSetTabIndexInWindow => 2,
+            SessionRestoreIdType::CommandTabNavigationPathPrunedFromBack => 5,
+            SessionRestoreIdType::CommandUpdateTabNavigation => 6,
+            SessionRestoreIdType::CommandSetSelectedNavigationIndex => 7,
+            SessionRestoreIdType::CommandSetSelectedTabInIndex => 8,
+            SessionRestoreIdType::CommandSetWindowType => 9,
+            SessionRestoreIdType::CommandSetWindowBounds2 => 10,
+            SessionRestoreIdType::CommandTabNavigationPathPrunedFromFront => 11,
+            SessionRestoreIdType::CommandSetPinnedState => 12,
+            SessionRestoreIdType::CommandSetExtensionAppID => 13,
+            SessionRestoreIdType::CommandSetWindowBounds3 => 14,
+            SessionRestoreIdType::CommandSetWindowAppName => 15,
+            SessionRestoreIdType::CommandTabClosed => 16,
+            SessionRestoreIdType::CommandWindowClosed => 17,
+            SessionRestoreIdType::CommandSetTabUserAgentOverride => 18,
+            SessionRestoreIdType::CommandSessionStorageAssociated => 19,
+            SessionRestoreIdType::CommandSetActiveWindow => 20,
+            SessionRestoreIdType::CommandLastActiveTime => 21,
+            SessionRestoreIdType::CommandSetWindowWorkspace => 22,
+            SessionRestoreIdType::CommandSetWindowWorkspace2 => 23,
+            SessionRestoreIdType::CommandTabNavigationPathPruned => 24,
+            SessionRestoreIdType::CommandSetTabGroup => 25,
+            SessionRestoreIdType::CommandSetTabGroupMetadata => 26,
+            SessionRestoreIdType::CommandSetTabGroupMetadata2 => 27,
+            SessionRestoreIdType::CommandSetTabGuid => 28,
+            SessionRestoreIdType::CommandSetTabUserAgentOverride2 => 29,
+            SessionRestoreIdType::CommandSetTabData => 30,
+            SessionRestoreIdType::CommandSetWindowUserTitle => 31,
+            SessionRestoreIdType::CommandSetWindowVisibleOnAllWorkspaces => 32,
+            SessionRestoreIdType::CommandAddTabExtraData => 33,
+            SessionRestoreIdType::CommandAddWindowExtraData => 34,
+            SessionRestoreIdType::EdgeCommandUnknown131 => 131,
+            SessionRestoreIdType::EdgeCommandUnknown132 => 132,
+            SessionRestoreIdType::UnusedCommand => 255,
+            SessionRestoreIdType::Unknown(value) => value,
+        }
+    }
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabRestoreIdType {
+    CommandUpdateTabNavigation = 1,
+    CommandRestoredEntry = 2,
+    CommandWindowDeprecated = 3,
+    CommandSelectedNavigationInTab = 4,
+    CommandPinnedState = 5,
+    CommandSetExtensionAppID = 6,
+    CommandSetWindowAppName = 7,
+    CommandSetTabUserAgentOverride = 8,
+    CommandWindow = 9,
+    CommandSetTabGroupData = 10,
+    CommandSetTabUserAgentOverride2 = 11,
+    CommandSetWindowUserTitle = 12,
+    CommandCreateGroup = 13,
+    CommandAddTabExtraData = 14,
+    UnusedCommand = 255,
+    Unknown(u8) = 254,
+}
+
+impl TabRestoreIdType {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => TabRestoreIdType::CommandUpdateTabNavigation,
+            2 => TabRestoreIdType::CommandRestoredEntry,
+            3 => TabRestoreIdType::CommandWindowDeprecated,
+            4 => TabRestoreIdType::CommandSelectedNavigationInTab,
+            5 => TabRestoreIdType::CommandPinnedState,
+            6 => TabRestoreIdType::CommandSetExtensionAppID,
+            7 => TabRestoreIdType::CommandSetWindowAppName,
+            8 => TabRestoreIdType::CommandSetTabUserAgentOverride,
+            9 => TabRestoreIdType::CommandWindow,
+            10 => TabRestoreIdType::CommandSetTabGroupData,
+            11 => TabRestoreIdType::CommandSetTabUserAgentOverride2,
+            12 => TabRestoreIdType::CommandSetWindowUserTitle,
+            13 => TabRestoreIdType::CommandCreateGroup,
+            14 => TabRestoreIdType::CommandAddTabExtraData,
+            255 => TabRestoreIdType::UnusedCommand,
+            unknown => TabRestoreIdType::Unknown(unknown),
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        match self {
+            TabRestoreIdType::CommandUpdateTabNavigation => 1,
+            TabRestoreIdType::CommandRestoredEntry => 2,
+            TabRestoreIdType::CommandWindowDeprecated => 3,
+            TabRestoreIdType::CommandSelectedNavigationInTab => 4,
+            TabRestoreIdType::CommandPinnedState => 5,
+            TabRestoreIdType::CommandSetExtensionAppID => 6,
+            TabRestoreIdType::CommandSetWindowAppName => 7,
+            TabRestoreIdType::CommandSetTabUserAgentOverride => 8,
+            TabRestoreIdType::CommandWindow => 9,
+            TabRestoreIdType::CommandSetTabGroupData => 10,
+            TabRestoreIdType::CommandSetTabUserAgentOverride2 => 11,
+            TabRestoreIdType::CommandSetWindowUserTitle => 12,
+            TabRestoreIdType::CommandCreateGroup => 13,
+            TabRestoreIdType::CommandAddTabExtraData => 14,
+            TabRestoreIdType::UnusedCommand => 255,
+            TabRestoreIdType::Unknown(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandIdType {
+    Session(SessionRestoreIdType),
+    Tab(TabRestoreIdType),
+    Invalid,
+}
+
+#[derive(Debug)]
+pub struct UnprocessedEntry {
+    command_type: CommandIdType,
+    offset: u64,
+    length: usize,
+}
+
+impl UnprocessedEntry {
+    pub fn command_type(&self) -> CommandIdType {
+        self.command_type
+    }
+
+    /// Offset of the command's length prefix from the start of the file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Length of the command including its id byte.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+}
+
+/// Payload of `CommandSetTabWindow`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TabWindow {
+    pub window_id: i32,
+    pub tab_id: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowShowState {
+    Default,
+    Normal,
+    Minimized,
+    Maximized,
+    Inactive,
+    Fullscreen,
+    Unknown(i32),
+}
+
+impl WindowShowState {
+    pub fn from_i32(value: i32) -> Self {
+        match value {
+            0 => WindowShowState::Default,
+            1 => WindowShowState::Normal,
+            2 => WindowShowState::Minimized,
+            3 => WindowShowState::Maximized,
+            4 => WindowShowState::Inactive,
+            5 => WindowShowState::Fullscreen,
+            unknown => WindowShowState::Unknown(unknown),
+        }
+    }
+
+    pub fn as_i32(self) -> i32 {
+        match self {
+            WindowShowState::Default => 0,
+            WindowShowState::Normal => 1,
+            WindowShowState::Minimized => 2,
+            WindowShowState::Maximized => 3,
+            WindowShowState::Inactive => 4,
+            WindowShowState::Fullscreen => 5,
+            WindowShowState::Unknown(value) => value,
+        }
+    }
+}
+
+/// Payload of the three `CommandSetWindowBounds` variants.
+///
+/// The first two carry an `is_maximized` flag in place of the show state;
+/// it is mapped onto [`WindowShowState::Maximized`] or
+/// [`WindowShowState::Normal`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowBounds {
+    pub window_id: i32,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub show_state: WindowShowState,
+}
+
+/// Payload of the commands that pair a tab or window id with an index,
+/// Chromium's `IDAndIndexPayload`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdAndIndex {
+    pub id: i32,
+    pub index: i32,
+}
+
+/// Payload of `CommandTabNavigationPathPruned`: `count` navigations
+/// starting at `index` were removed from the tab.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NavigationPathPruned {
+    pub id: i32,
+    pub index: i32,
+    pub count: i32,
+}
+
+/// Payload of `CommandSetPinnedState`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PinnedState {
+    pub tab_id: i32,
+    pub pinned: bool,
+}
+
+/// Payload of `CommandTabClosed`, `CommandWindowClosed` and
+/// `CommandLastActiveTime`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdAndTime {
+    pub id: i32,
+    pub time: ChromeTime,
+}
+
+/// A `tab_groups::TabGroupId`: a random 128-bit token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, FromPickle, ToPickle)]
+pub struct TabGroupId {
+    pub high: u64,
+    pub low: u64,
+}
+
+/// Formats as 32 hexadecimal digits, like `base::Token::ToString`.
+impl fmt::Display for TabGroupId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016X}{:016X}", self.high, self.low)
+    }
+}
+
+/// Payload of `CommandSetTabGroup`; `group` is `None` when the tab left
+/// its group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TabGroupMembership {
+    pub tab_id: i32,
+    pub group: Option<TabGroupId>,
+}
+
+/// `tab_groups::TabGroupColorId`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabGroupColor {
+    Grey,
+    Blue,
+    Red,
+    Yellow,
+    Green,
+    Pink,
+    Purple,
+    Cyan,
+    Orange,
+    Unknown(u32),
+}
+
+impl TabGroupColor {
+    pub fn from_u32(value: u32) -> Self {
+        match value {
+            0 => TabGroupColor::Grey,
+            1 => TabGroupColor::Blue,
+            2 => TabGroupColor::Red,
+            3 => TabGroupColor::Yellow,
+            4 => TabGroupColor::Green,
+            5 => TabGroupColor::Pink,
+            6 => TabGroupColor::Purple,
+            7 => TabGroupColor::Cyan,
+            8 => TabGroupColor::Orange,
+            unknown => TabGroupColor::Unknown(unknown),
+        }
+    }
+
+    pub fn as_u32(self) -> u32 {
+        match self {
+            TabGroupColor::Grey => 0,
+            TabGroupColor::Blue => 1,
+            TabGroupColor::Red => 2,
+            TabGroupColor::Yellow => 3,
+            TabGroupColor::Green => 4,
+            TabGroupColor::Pink => 5,
+            TabGroupColor::Purple => 6,
+            TabGroupColor::Cyan => 7,
+            TabGroupColor::Orange => 8,
+            TabGroupColor::Unknown(value) => value,
+        }
+    }
+}
+
+impl FromPickle for TabGroupColor {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        pickle.read_uint32().map(TabGroupColor::from_u32)
+    }
+}
+
+impl ToPickle for TabGroupColor {
+    fn to_pickle(&self, pickle: &mut PickleWriter) {
+        pickle.write_uint32(self.as_u32());
+    }
+}
+
+/// Payload of `CommandSetTabGroupMetadata2`.
+#[derive(Debug, Clone, PartialEq, Eq, FromPickle, ToPickle)]
+pub struct TabGroupMetadata {
+    pub group: TabGroupId,
+    pub title: String16,
+    pub color: TabGroupColor,
+}
+
+/// Payload of `CommandSetTabGuid`.
+#[derive(Debug, Clone, PartialEq, Eq, FromPickle, ToPickle)]
+pub struct TabGuid {
+    pub tab_id: i32,
+    pub guid: String,
+}
+
+/// Payload of the tab-restore `CommandSelectedNavigationInTab`, which
+/// starts a closed tab entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectedNavigationInTab {
+    pub id: i32,
+    pub index: i32,
+    /// When the tab was closed.
+    pub timestamp: ChromeTime,
+}
+
+/// The leading fields of the tab-restore `CommandWindow`, which starts a
+/// closed window entry followed by its `num_tabs` tabs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPickle, ToPickle)]
+pub struct ClosedWindow {
+    pub id: i32,
+    pub selected_tab_index: i32,
+    pub num_tabs: i32,
+    /// When the window was closed.
+    pub timestamp: ChromeTime,
+}
+
+#[derive(Debug)]
+pub enum SessionCommand {
+    NavigationEntry(Box<NavigationEntry>),
+    TabWindow(TabWindow),
+    WindowBounds(WindowBounds),
+    TabIndexInWindow(IdAndIndex),
+    SelectedNavigationIndex(IdAndIndex),
+    /// Index of the selected tab of a window.
+    SelectedTabInIndex(IdAndIndex),
+    /// Window id and its `SessionWindow::WindowType`.
+    WindowType(IdAndIndex),
+    /// Tab id and the number of navigations that remain.
+    TabNavigationPathPrunedFromBack(IdAndIndex),
+    /// Tab id and the number of navigations removed from the front.
+    TabNavigationPathPrunedFromFront(IdAndIndex),
+    /// Navigations removed from the middle of a tab's history, which
+    /// current versions write in place of the two commands above.
+    TabNavigationPathPruned(NavigationPathPruned),
+    PinnedState(PinnedState),
+    TabClosed(IdAndTime),
+    WindowClosed(IdAndTime),
+    ActiveWindow(i32),
+    LastActiveTime(IdAndTime),
+    TabGroup(TabGroupMembership),
+    TabGroupMetadata(TabGroupMetadata),
+    TabGuid(TabGuid),
+    SelectedNavigationInTab(SelectedNavigationInTab),
+    ClosedWindow(ClosedWindow),
+    /// The tab-restore pinned state of the closed tab being read.
+    EntryPinned(bool),
+    /// A closed tab or window that was restored again, by entry id.
+    RestoredEntry(i32),
+    /// Separates the snapshot Chromium writes when it opens a v3 file from
+    /// the commands appended afterwards.
+    InitialStateMarker,
+    UnprocessedEntry(UnprocessedEntry),
+    EOF,
+}
+
+/// Chromium's `network::mojom::ReferrerPolicy`, as stored in the
+/// `referrer_policy` field of a navigation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferrerPolicy {
+    /// `unsafe-url`.
+    Always,
+    Default,
+    NoReferrerWhenDowngrade,
+    /// `no-referrer`.
+    Never,
+    Origin,
+    OriginWhenCrossOrigin,
+    StrictOriginWhenCrossOrigin,
+    SameOrigin,
+    StrictOrigin,
+    Unknown(i32),
+}
+
+impl ReferrerPolicy {
+    pub fn from_i32(value: i32) -> Self {
+        match value {
+            0 => ReferrerPolicy::Always,
+            1 => ReferrerPolicy::Default,
+            2 => ReferrerPolicy::NoReferrerWhenDowngrade,
+            3 => ReferrerPolicy::Never,
+            4 => ReferrerPolicy::Origin,
+            5 => ReferrerPolicy::OriginWhenCrossOrigin,
+            6 => ReferrerPolicy::StrictOriginWhenCrossOrigin,
+            7 => ReferrerPolicy::SameOrigin,
+            8 => ReferrerPolicy::StrictOrigin,
+            unknown => ReferrerPolicy::Unknown(unknown),
+        }
+    }
+
+    pub fn as_i32(self) -> i32 {
+        match self {
+            ReferrerPolicy::Always => 0,
+            ReferrerPolicy::Default => 1,
+            ReferrerPolicy::NoReferrerWhenDowngrade => 2,
+            ReferrerPolicy::Never => 3,
+            ReferrerPolicy::Origin => 4,
+            ReferrerPolicy::OriginWhenCrossOrigin => 5,
+            ReferrerPolicy::StrictOriginWhenCrossOrigin => 6,
+            ReferrerPolicy::SameOrigin => 7,
+            ReferrerPolicy::StrictOrigin => 8,
+            ReferrerPolicy::Unknown(value) => value,
+        }
+    }
+
+    /// The `Referrer-Policy` header token, e.g. `strict-origin`. `None`
+    /// for [`ReferrerPolicy::Default`], which has no token, and for
+    /// unknown values.
+    pub fn token(self) -> Option<&'static str> {
+        match self {
+            ReferrerPolicy::Always => Some("unsafe-url"),
+            ReferrerPolicy::NoReferrerWhenDowngrade => Some("no-referrer-when-downgrade"),
+            ReferrerPolicy::Never => Some("no-referrer"),
+            ReferrerPolicy::Origin => Some("origin"),
+            ReferrerPolicy::OriginWhenCrossOrigin => Some("origin-when-cross-origin"),
+            ReferrerPolicy::StrictOriginWhenCrossOrigin => Some("strict-origin-when-cross-origin"),
+            ReferrerPolicy::SameOrigin => Some("same-origin"),
+            ReferrerPolicy::StrictOrigin => Some("strict-origin"),
+            ReferrerPolicy::Default | ReferrerPolicy::Unknown(_) => None,
+        }
+    }
+}
+
+bitflags! {
+    /// `SerializedNavigationEntry`'s type mask. Bits Chromium does not
+    /// define are kept as read.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct TypeMask: u32 {
+        /// The navigation was a POST.
+        const HasPostData = 0x01;
+        const _ = !0;
+    }
+}
+
+/// One entry of a tab's back/forward list, as written by Chromium's
+/// `SerializedNavigationEntry::WriteToPickle`.
+///
+/// Everything after the transition type was added to the format over time;
+/// the accessors for those fields return `None` (or the field's default)
+/// when the entry predates them or the field failed to decode, see
+/// [`NavigationEntry::warnings`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationEntry {
+    session_id: i32,
+    index: i32,
+    url: String,
+    title: String16,
+    page_state_raw: Vec<u8>,
+    transition_type: PageTransition,
+    type_mask: TypeMask,
+    unknown: i32,
+    referrer_url: Option<String>,
+    original_request_url: Option<String>,
+    is_overriding_user_agent: Option<bool>,
+    search_terms: Option<String16>,
+    timestamp: ChromeTime,
+    http_status: Option<i32>,
+    referrer_policy: Option<ReferrerPolicy>,
+    extended_map: HashMap<String, String>,
+    task_id: Option<i64>,
+    parent_task_id: Option<i64>,
+    root_task_id: Option<i64>,
+    child_task_id_count: Option<i32>,
+    warnings: Vec<FieldWarning>,
+}
+
+/// A navigation field that was present but failed to decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldWarning {
+    pub field: &'static str,
+    /// Offset of the field within the pickle.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FieldWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at offset {}: {}",
+            self.field, self.offset, self.message
+        )
+    }
+}
+
+/// Reads the fields Chromium appended to navigations over time.
+///
+/// A field is absent when the pickle ends before it, which is how older
+/// versions wrote entries. A field that is present but fails to decode
+/// leaves the position somewhere inside it, so it is recorded as a warning
+/// and none of the fields after it are read.
+struct TrailingFields<'a> {
+    pickle: &'a mut PickleIterator,
+    warnings: Vec<FieldWarning>,
+}
+
+impl TrailingFields<'_> {
+    fn read<T>(
+        &mut self,
+        field: &'static str,
+        read: impl FnOnce(&mut PickleIterator) -> Result<T, PickleError>,
+    ) -> Option<T> {
+        if !self.warnings.is_empty() || self.pickle.remaining() == 0 {
+            return None;
+        }
+
+        let offset = self.pickle.offset();
+        match read(self.pickle) {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.warnings.push(FieldWarning {
+                    field,
+                    offset,
+                    message: error.to_string(),
+                });
+                None
+            }
+        }
+    }
+}
+
+impl NavigationEntry {
+    pub fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        let session_id = pickle.read_int32()?;
+        let index = pickle.read_int32()?;
+        let url = pickle.read_string()?;
+        let title = pickle.read_string16_lossy()?;
+        let page_state_length = pickle.read_int32()?;
+        let page_state_raw = pickle.read_aligned(page_state_length as usize)?;
+        let transition_type_value = pickle.read_uint32()?;
+        let transition_type = PageTransition::new(transition_type_value);
+
+        let mut trailing = TrailingFields {
+            pickle,
+            warnings: Vec::new(),
+        };
+        let type_mask = trailing
+            .read("type_mask", PickleIterator::read_uint32)
+            .map(TypeMask::from_bits_retain)
+            .unwrap_or_default();
+        let referrer_url = trailing.read("referrer_url", PickleIterator::read_string);
+        let unknown = trailing
+            .read("unknown", PickleIterator::read_int32)
+            .unwrap_or_default();
+        let original_request_url =
+            trailing.read("original_request_url", PickleIterator::read_string);
+        let is_overriding_user_agent =
+            trailing.read("is_overriding_user_agent", PickleIterator::read_bool);
+        let timestamp = trailing
+            .read("timestamp", PickleIterator::read_datetime)
+            .unwrap_or_default();
+        let search_terms = trailing.read("search_terms", PickleIterator::read_string16_lossy);
+        let http_status = trailing.read("http_status", PickleIterator::read_int32);
+        let referrer_policy = trailing
+            .read("referrer_policy", PickleIterator::read_int32)
+            .map(ReferrerPolicy::from_i32);
+        let extended_map = trailing
+            .read("extended_map", HashMap::<String, String>::from_pickle)
+            .unwrap_or_default();
+        let task_id = trailing.read("task_id", PickleIterator::read_int64);
+        let parent_task_id = trailing.read("parent_task_id", PickleIterator::read_int64);
+        let root_task_id = trailing.read("root_task_id", PickleIterator::read_int64);
+        let child_task_id_count = trailing.read("child_task_id_count", PickleIterator::read_int32);
+
+        // Construct the NavigationEntry
+        Ok(NavigationEntry {
+            session_id,
+            index,
+            url,
+            title,
+            page_state_raw,
+            transition_type,
+            type_mask,
+            referrer_url,
+            unknown,
+            original_request_url,
+            is_overriding_user_agent,
+            timestamp,
+            search_terms,
+            http_status,
+            referrer_policy,
+            extended_map,
+            task_id,
+            parent_task_id,
+            root_task_id,
+            child_task_id_count,
+            warnings: trailing.warnings,
+        })
+    }
+
+    /// An entry for writing a file, with an empty page state and Chromium's
+    /// defaults for everything else. Use the `with_` methods to fill it in.
+    pub fn new(session_id: i32, index: i32, url: impl Into<String>) -> Self {
+        NavigationEntry {
+            session_id,
+            index,
+            url: url.into(),
+            title: String16::default(),
+            page_state_raw: Vec::new(),
+            transition_type: PageTransition::new(0),
+            type_mask: TypeMask::empty(),
+            unknown: 0,
+            referrer_url: None,
+            original_request_url: None,
+            is_overriding_user_agent: None,
+            search_terms: None,
+            timestamp: ChromeTime::default(),
+            http_status: None,
+            referrer_policy: Some(ReferrerPolicy::Default),
+            extended_map: HashMap::new(),
+            task_id: None,
+            parent_task_id: None,
+            root_task_id: None,
+            child_task_id_count: None,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = String16::from(title);
+        self
+    }
+
+    pub fn with_transition(mut self, transition: PageTransition) -> Self {
+        self.transition_type = transition;
+        self
+    }
+
+    pub fn with_referrer(mut self, url: impl Into<String>, policy: ReferrerPolicy) -> Self {
+        self.referrer_url = Some(url.into());
+        self.referrer_policy = Some(policy);
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: ChromeTime) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn with_http_status(mut self, status: i32) -> Self {
+        self.http_status = Some(status);
+        self
+    }
+
+    /// Fields that were present in the pickle but could not be decoded.
+    /// They and every field after them are reported as absent.
+    pub fn warnings(&self) -> &[FieldWarning] {
+        &self.warnings
+    }
+
+    /// Id of the tab in session files, or of the closed tab or window
+    /// entry in tab-restore files.
+    pub fn session_id(&self) -> i32 {
+        self.session_id
+    }
+
+    /// Position of the entry in the tab's back/forward list.
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+
+    /// Renumbers the entry after entries before it were pruned.
+    pub(crate) fn set_index(&mut self, index: i32) {
+        self.index = index;
+    }
+
+    /// Moves the entry to a renumbered tab.
+    pub(crate) fn set_session_id(&mut self, session_id: i32) {
+        self.session_id = session_id;
+    }
+
+    /// The URL shown for the entry. Chromium only serializes the virtual
+    /// URL, so this is the same as [`NavigationEntry::virtual_url`].
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The URL displayed in the omnibox, which may differ from the URL
+    /// that was actually loaded (e.g. `view-source:` pages).
+    pub fn virtual_url(&self) -> &str {
+        &self.url
+    }
+
+    /// The URL originally requested, before any redirects.
+    pub fn original_request_url(&self) -> Option<&str> {
+        self.original_request_url.as_deref()
+    }
+
+    pub fn referrer_url(&self) -> Option<&str> {
+        self.referrer_url.as_deref()
+    }
+
+    pub fn title(&self) -> &str {
+        self.title.as_str()
+    }
+
+    /// The title with its original UTF-16 code units.
+    pub fn title16(&self) -> &String16 {
+        &self.title
+    }
+
+    /// The encoded `blink::PageState` (form data, scroll position, ...).
+    pub fn page_state(&self) -> &[u8] {
+        &self.page_state_raw
+    }
+
+    pub fn transition(&self) -> &PageTransition {
+        &self.transition_type
+    }
+
+    pub fn type_mask(&self) -> TypeMask {
+        self.type_mask
+    }
+
+    pub fn has_post_data(&self) -> bool {
+        self.type_mask.contains(TypeMask::HasPostData)
+    }
+
+    /// Referrer policy field that Chromium writes before the original
+    /// request URL. It predates the current policy numbering and is kept
+    /// for compatibility; prefer [`NavigationEntry::referrer_policy`].
+    pub fn obsolete_referrer_policy(&self) -> i32 {
+        self.unknown
+    }
+
+    pub fn referrer_policy(&self) -> Option<ReferrerPolicy> {
+        self.referrer_policy
+    }
+
+    pub fn is_overriding_user_agent(&self) -> Option<bool> {
+        self.is_overriding_user_agent
+    }
+
+    /// When the navigation was committed. Null for entries that predate
+    /// the field.
+    pub fn timestamp(&self) -> ChromeTime {
+        self.timestamp
+    }
+
+    /// Always empty in files written by current Chromium.
+    pub fn search_terms(&self) -> Option<&str> {
+        self.search_terms.as_ref().map(String16::as_str)
+    }
+
+    pub fn http_status(&self) -> Option<i32> {
+        self.http_status
+    }
+
+    /// Extended info stored by other browser components, keyed by name.
+    pub fn extended_map(&self) -> &HashMap<String, String> {
+        &self.extended_map
+    }
+
+    /// The extended info map decoded with the default
+    /// [`ExtendedInfoRegistry`].
+    pub fn extended_info(&self) -> BTreeMap<&str, ExtendedValue> {
+        self.extended_info_with(&ExtendedInfoRegistry::default())
+    }
+
+    pub fn extended_info_with(
+        &self,
+        registry: &ExtendedInfoRegistry,
+    ) -> BTreeMap<&str, ExtendedValue> {
+        registry.decode_map(&self.extended_map)
+    }
+
+    pub fn task_id(&self) -> Option<i64> {
+        self.task_id
+    }
+
+    pub fn parent_task_id(&self) -> Option<i64> {
+        self.parent_task_id
+    }
+
+    pub fn root_task_id(&self) -> Option<i64> {
+        self.root_task_id
+    }
+
+    /// Obsolete; Chromium now always writes zero.
+    pub fn child_task_id_count(&self) -> Option<i32> {
+        self.child_task_id_count
+    }
+
+    #[cfg(feature = "url")]
+    pub fn parsed_url(&self) -> Result<url::Url, url::ParseError> {
+        url::Url::parse(&self.url)
+    }
+
+    /// The referrer, if present and not empty.
+    #[cfg(feature = "url")]
+    pub fn parsed_referrer_url(&self) -> Option<Result<url::Url, url::ParseError>> {
+        self.referrer_url
+            .as_deref()
+            .filter(|url| !url.is_empty())
+            .map(url::Url::parse)
+    }
+
+    /// The original request URL, if present and not empty.
+    #[cfg(feature = "url")]
+    pub fn parsed_original_request_url(&self) -> Option<Result<url::Url, url::ParseError>> {
+        self.original_request_url
+            .as_deref()
+            .filter(|url| !url.is_empty())
+            .map(url::Url::parse)
+    }
+}
+
+impl FromPickle for NavigationEntry {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        NavigationEntry::from_pickle(pickle)
+    }
+}
+
+/// Writes every field the way current Chromium does, substituting the
+/// defaults for optional fields that were missing when the entry was read.
+impl ToPickle for NavigationEntry {
+    fn to_pickle(&self, pickle: &mut PickleWriter) {
+        pickle.write_int32(self.session_id);
+        pickle.write_int32(self.index);
+        pickle.write_string(&self.url);
+        self.title.to_pickle(pickle);
+        pickle.write_bytes(&self.page_state_raw);
+        pickle.write_uint32(self.transition_type.value());
+        pickle.write_uint32(self.type_mask.bits());
+        pickle.write_string(self.referrer_url.as_deref().unwrap_or_default());
+        pickle.write_int32(self.unknown);
+        pickle.write_string(self.original_request_url.as_deref().unwrap_or_default());
+        pickle.write_bool(self.is_overriding_user_agent.unwrap_or_default());
+        pickle.write_datetime(self.timestamp);
+        self.search_terms
+            .clone()
+            .unwrap_or_default()
+            .to_pickle(pickle);
+        pickle.write_int32(self.http_status.unwrap_or_default());
+        pickle.write_int32(
+            self.referrer_policy
+                .map_or(ReferrerPolicy::Default.as_i32(), ReferrerPolicy::as_i32),
+        );
+        self.extended_map.to_pickle(pickle);
+        pickle.write_int64(self.task_id.unwrap_or(-1));
+        pickle.write_int64(self.parent_task_id.unwrap_or(-1));
+        pickle.write_int64(self.root_task_id.unwrap_or(-1));
+        pickle.write_int32(self.child_task_id_count.unwrap_or_default());
+    }
+}
+
+/// Size of the `SNSS` magic plus the version that precede the commands.
+pub(crate) const HEADER_LENGTH: u64 = 8;
+
+#[derive(Debug)]
+pub struct SnssFile {
+    file_type: SnssFileType,
+    version: SnssVersion,
+    created: Option<SystemTime>,
+    cursor: Cursor<Vec<u8>>,
+}
+
+impl SnssFile {
+    pub fn new<R: Read>(file_type: SnssFileType, mut file: R) -> Result<Self, SnssError> {
+        let mut header = [0u8; HEADER_LENGTH as usize];
+        file.read_exact(&mut header)?;
+        let version = parse_header(&header)?;
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        let cursor = Cursor::new(data);
+
+        Ok(Self {
+            file_type,
+            version,
+            created: None,
+            cursor,
+        })
+    }
+
+    /// Opens a file by path. The type and creation time are taken from the
+    /// file name when it follows Chromium's naming; otherwise the type is
+    /// detected from the contents and the detection is returned as well.
+    pub fn open(path: &Path) -> Result<(Self, Option<Detection>), SnssError> {
+        let name = path.file_name().and_then(|name| name.to_str());
+        let file = File::open(path)?;
+        let (mut snss_file, detection) = match name.and_then(SnssFileType::from_file_name) {
+            Some(file_type) => (Self::new(file_type, file)?, None),
+            None => {
+                let (snss_file, detection) = Self::detect(file)?;
+                (snss_file, Some(detection))
+            }
+        };
+        snss_file.created = name.and_then(file_name_timestamp);
+        Ok((snss_file, detection))
+    }
+
+    /// Opens a file whose type is unknown, for instance a renamed or carved
+    /// one, and infers it from the commands it contains.
+    pub fn detect<R: Read>(file: R) -> Result<(Self, Detection), SnssError> {
+        let mut snss_file = Self::new(SnssFileType::Session, file)?;
+        let mut detector = detect::Detector::default();
+        // A truncated trailing command still leaves the earlier evidence.
+        while let Ok(Some((_, data))) = snss_file.read_raw_command() {
+            if let Some((&command_id, payload)) = data.split_first() {
+                detector.add(command_id, payload);
+            }
+        }
+
+        let detection = detector.finish();
+        snss_file.file_type = detection.file_type;
+        snss_file.reset();
+        Ok((snss_file, detection))
+    }
+
+    pub fn file_type(&self) -> SnssFileType {
+        self.file_type
+    }
+
+    pub fn version(&self) -> SnssVersion {
+        self.version
+    }
+
+    /// Creation time from the file name, if the file was opened with
+    /// [`SnssFile::open`] and carries a `Session_`/`Tabs_` timestamp.
+    pub fn created(&self) -> Option<SystemTime> {
+        self.created
+    }
+
+    /// Rewinds to the first command after the header.
+    pub fn reset(&mut self) {
+        self.cursor.set_position(0);
+    }
+
+    pub fn iter_session_commands(
+        &mut self,
+    ) -> impl Iterator<Item = Result<SessionCommand, SnssError>> + '_ {
+        self.iter_commands_with_offsets()
+            .map(|command| command.map(|(_, command)| command))
+    }
+
+    /// Like [`SnssFile::iter_session_commands`], with the offset of each
+    /// command's length prefix from the start of the file.
+    pub fn iter_commands_with_offsets(
+        &mut self,
+    ) -> impl Iterator<Item = Result<(u64, SessionCommand), SnssError>> + '_ {
+        std::iter::from_fn(move || match self.get_next_session_command() {
+            Ok((_, SessionCommand::EOF)) => None,
+            command => Some(command),
+        })
+    }
+
+    /// Reads the next length-prefixed command without interpreting it,
+    /// returning its position in the data after the header.
+    fn read_raw_command(&mut self) -> Result<Option<(u64, Vec<u8>)>, SnssError> {
+        let start_pos = self.cursor.position();
+        let length = match self.cursor.read_u16::<LittleEndian>() {
+            Ok(len) => len,
+            Err(_) => return Ok(None),
+        };
+
+        let mut data = vec![0u8; length as usize];
+        self.cursor.read_exact(&mut data)?;
+        Ok(Some((start_pos, data)))
+    }
+
+    fn get_next_session_command(&mut self) -> Result<(u64, SessionCommand), SnssError> {
+        let (start_pos, data) = match self.read_raw_command()? {
+            Some(raw) => raw,
+            None => return Ok((self.cursor.position() + HEADER_LENGTH, SessionCommand::EOF)),
+        };
+        let offset = start_pos + HEADER_LENGTH;
+        let decoded = decode_command(self.file_type, self.version, offset, &data)?;
+        Ok((offset, decoded))
+    }
+}
+
+/// Checks the `SNSS` magic and returns the version that follows it.
+pub(crate) fn parse_header(
+    header: &[u8; HEADER_LENGTH as usize],
+) -> Result<SnssVersion, SnssError> {
+    if &header[0..4] != b"SNSS" {
+        return Err(SnssError::InvalidMagic);
+    }
+    let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    SnssVersion::from_u32(version)
+}
+
+/// Decodes one command, its id byte followed by the payload, read at
+/// `offset` from a file of the given type and version.
+pub(crate) fn decode_command(
+    file_type: SnssFileType,
+    version: SnssVersion,
+    offset: u64,
+    data: &[u8],
+) -> Result<SessionCommand, SnssError> {
+    let length = data.len();
+    let Some((&command_id, payload)) = data.split_first() else {
+        return Err(SnssError::EmptyCommand(offset));
+    };
+
+    let command = match file_type {
+        SnssFileType::Session => CommandIdType::Session(SessionRestoreIdType::from_u8(command_id)),
+        SnssFileType::Tab => CommandIdType::Tab(TabRestoreIdType::from_u8(command_id)),
+    };
+
+    let decoded = match command {
+        CommandIdType::Session(SessionRestoreIdType::CommandUpdateTabNavigation)
+        | CommandIdType::Tab(TabRestoreIdType::CommandUpdateTabNavigation) => {
+            let mut pickle = PickleIterator::new(payload.to_vec(), 4)?;
+            let nav = NavigationEntry::from_pickle(&mut pickle)?;
+            Some(SessionCommand::NavigationEntry(Box::new(nav)))
+        }
+        CommandIdType::Session(SessionRestoreIdType::UnusedCommand)
+        | CommandIdType::Tab(TabRestoreIdType::UnusedCommand)
+            if version.has_marker() =>
+        {
+            Some(SessionCommand::InitialStateMarker)
+        }
+        CommandIdType::Session(session) => decode_session_payload(session, payload)?,
+        CommandIdType::Tab(tab) => decode_tab_payload(tab, payload)?,
+        CommandIdType::Invalid => None,
+    };
+
+    Ok(
+        decoded.unwrap_or(SessionCommand::UnprocessedEntry(UnprocessedEntry {
+            command_type: command,
+            length,
+            offset,
+        })),
+    )
+}
+
+/// Decodes the fixed-layout payloads of session commands. These are plain
+/// structs copied out of memory by Chromium rather than pickles, and the
+/// layout is selected by the command id rather than the file version.
+fn decode_session_payload(
+    id: SessionRestoreIdType,
+    payload: &[u8],
+) -> Result<Option<SessionCommand>, SnssError> {
+    let mut payload = Cursor::new(payload);
+    let command = match id {
+        SessionRestoreIdType::CommandSetTabWindow => SessionCommand::TabWindow(TabWindow {
+            window_id: payload.read_i32::<LittleEndian>()?,
+            tab_id: payload.read_i32::<LittleEndian>()?,
+        }),
+        SessionRestoreIdType::CommandSetWindowBounds
+        | SessionRestoreIdType::CommandSetWindowBounds2
+        | SessionRestoreIdType::CommandSetWindowBounds3 => {
+            let window_id = payload.read_i32::<LittleEndian>()?;
+            let x = payload.read_i32::<LittleEndian>()?;
+            let y = payload.read_i32::<LittleEndian>()?;
+            let width = payload.read_i32::<LittleEndian>()?;
+            let height = payload.read_i32::<LittleEndian>()?;
+            let show_state = if id == SessionRestoreIdType::CommandSetWindowBounds3 {
+                WindowShowState::from_i32(payload.read_i32::<LittleEndian>()?)
+            } else if payload.read_u8()? != 0 {
+                WindowShowState::Maximized
+            } else {
+                WindowShowState::Normal
+            };
+            SessionCommand::WindowBounds(WindowBounds {
+                window_id,
+                x,
+                y,
+                width,
+                height,
+                show_state,
+            })
+        }
+        SessionRestoreIdType::CommandSetTabIndexInWindow => {
+            SessionCommand::TabIndexInWindow(read_id_and_index(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandSetSelectedNavigationIndex => {
+            SessionCommand::SelectedNavigationIndex(read_id_and_index(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandSetSelectedTabInIndex => {
+            SessionCommand::SelectedTabInIndex(read_id_and_index(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandSetWindowType => {
+            SessionCommand::WindowType(read_id_and_index(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandTabNavigationPathPrunedFromBack => {
+            SessionCommand::TabNavigationPathPrunedFromBack(read_id_and_index(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandTabNavigationPathPrunedFromFront => {
+            SessionCommand::TabNavigationPathPrunedFromFront(read_id_and_index(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandTabNavigationPathPruned => {
+            SessionCommand::TabNavigationPathPruned(NavigationPathPruned {
+                id: payload.read_i32::<LittleEndian>()?,
+                index: payload.read_i32::<LittleEndian>()?,
+                count: payload.read_i32::<LittleEndian>()?,
+            })
+        }
+        SessionRestoreIdType::CommandSetPinnedState => SessionCommand::PinnedState(PinnedState {
+            tab_id: payload.read_i32::<LittleEndian>()?,
+            pinned: payload.read_u8()? != 0,
+        }),
+        SessionRestoreIdType::CommandTabClosed => {
+            SessionCommand::TabClosed(read_id_and_time(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandWindowClosed => {
+            SessionCommand::WindowClosed(read_id_and_time(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandLastActiveTime => {
+            SessionCommand::LastActiveTime(read_id_and_time(&mut payload)?)
+        }
+        SessionRestoreIdType::CommandSetActiveWindow => {
+            SessionCommand::ActiveWindow(payload.read_i32::<LittleEndian>()?)
+        }
+        SessionRestoreIdType::CommandSetTabGroup => {
+            let tab_id = payload.read_i32::<LittleEndian>()?;
+            payload.read_u32::<LittleEndian>()?; // padding
+            let group = TabGroupId {
+                high: payload.read_u64::<LittleEndian>()?,
+                low: payload.read_u64::<LittleEndian>()?,
+            };
+            let has_group = payload.read_u8()? != 0;
+            SessionCommand::TabGroup(TabGroupMembership {
+                tab_id,
+                group: has_group.then_some(group),
+            })
+        }
+        SessionRestoreIdType::CommandSetTabGroupMetadata2 => {
+            let mut pickle = PickleIterator::new(payload.into_inner().to_vec(), 4)?;
+            SessionCommand::TabGroupMetadata(TabGroupMetadata::from_pickle(&mut pickle)?)
+        }
+        SessionRestoreIdType::CommandSetTabGuid => {
+            let mut pickle = PickleIterator::new(payload.into_inner().to_vec(), 4)?;
+            SessionCommand::TabGuid(TabGuid::from_pickle(&mut pickle)?)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(command))
+}
+
+/// Decodes the payloads of tab-restore commands other than navigations.
+fn decode_tab_payload(
+    id: TabRestoreIdType,
+    payload: &[u8],
+) -> Result<Option<SessionCommand>, SnssError> {
+    let mut cursor = Cursor::new(payload);
+    let command = match id {
+        TabRestoreIdType::CommandSelectedNavigationInTab => {
+            let id = cursor.read_i32::<LittleEndian>()?;
+            let index = cursor.read_i32::<LittleEndian>()?;
+            // Older versions wrote only the id and the index.
+            let timestamp = if payload.len() >= 16 {
+                ChromeTime::from_internal(cursor.read_i64::<LittleEndian>()?)
+            } else {
+                ChromeTime::default()
+            };
+            SessionCommand::SelectedNavigationInTab(SelectedNavigationInTab {
+                id,
+                index,
+                timestamp,
+            })
+        }
+        TabRestoreIdType::CommandPinnedState => SessionCommand::EntryPinned(cursor.read_u8()? != 0),
+        TabRestoreIdType::CommandRestoredEntry => {
+            SessionCommand::RestoredEntry(cursor.read_i32::<LittleEndian>()?)
+        }
+        TabRestoreIdType::CommandWindow => {
+            let mut pickle = PickleIterator::new(payload.to_vec(), 4)?;
+            SessionCommand::ClosedWindow(ClosedWindow::from_pickle(&mut pickle)?)
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(command))
+}
+
+fn read_id_and_index(payload: &mut Cursor<&[u8]>) -> io::Result<IdAndIndex> {
+    Ok(IdAndIndex {
+        id: payload.read_i32::<LittleEndian>()?,
+        index: payload.read_i32::<LittleEndian>()?,
+    })
+}
+
+/// Reads an id followed by a 64-bit time, which Chromium aligns to eight
+/// bytes.
+fn read_id_and_time(payload: &mut Cursor<&[u8]>) -> io::Result<IdAndTime> {
+    let id = payload.read_i32::<LittleEndian>()?;
+    payload.read_u32::<LittleEndian>()?; // padding
+    Ok(IdAndTime {
+        id,
+        time: ChromeTime::from_internal(payload.read_i64::<LittleEndian>()?),
+    })
+}
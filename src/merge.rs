@@ -0,0 +1,244 @@
+//! Merges several session files into one session that can be restored.
+//!
+//! After a crash a profile can be left with several partial `Session_`
+//! files. Merging unions their open windows and tabs: copies of the same
+//! tab are recognised by GUID, or by session id when they also share a
+//! navigation, since session ids restart with every browser run. Of the
+//! copies, the one with the most recent navigation or activity is kept.
+//! Windows that share a tab are merged into one.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::session::{
+    SessionState, Tab, Window, group_metadata_command, is_same_tab, push_window_commands,
+};
+use crate::time::ChromeTime;
+use crate::{SessionCommand, SnssFileType};
+
+/// A window of one of the merged states; `None` for tabs without one.
+type WindowKey = (usize, Option<i32>);
+
+/// One copy of a tab, from the state at `source`.
+#[derive(Clone, Copy)]
+struct TabCopy<'a> {
+    source: usize,
+    tab: &'a Tab,
+}
+
+impl TabCopy<'_> {
+    fn window(&self) -> WindowKey {
+        (self.source, self.tab.window_id)
+    }
+
+    /// When the tab was last used, as far as the file tells.
+    fn latest(&self) -> ChromeTime {
+        self.tab
+            .navigations
+            .iter()
+            .map(|navigation| navigation.entry.timestamp())
+            .chain(self.tab.last_active)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Merges the open windows and tabs of session files into one state.
+///
+/// States are expected oldest first: when copies of a tab are equally
+/// recent, and for group names and the active window, later states win.
+/// Each merged window takes its bounds, selected tab and tab order from
+/// the window most of its tabs were kept from; the other tabs follow.
+/// Closed tabs and windows, tabs without navigations and tab-restore files
+/// are left out. Windows and tabs are renumbered from 1, and offsets of
+/// the result are 0.
+pub fn merge(states: &[&SessionState]) -> SessionState {
+    let copies = collect_copies(states);
+
+    let mut windows = WindowSets::default();
+    for tab_copies in &copies {
+        for copy in tab_copies {
+            windows.union(tab_copies[0].window(), copy.window());
+        }
+    }
+
+    // Tabs of each merged window, keyed by the window its first copy came
+    // from so windows keep the order of the files.
+    let mut merged: BTreeMap<WindowKey, Vec<&[TabCopy]>> = BTreeMap::new();
+    for tab_copies in &copies {
+        let root = windows.find(tab_copies[0].window());
+        merged.entry(root).or_default().push(tab_copies);
+    }
+
+    let mut commands = Vec::new();
+    let mut groups = BTreeMap::new();
+    let mut next_id = 1;
+    let mut active_window = None;
+    let active = states.iter().enumerate().rev().find_map(|(source, state)| {
+        let key = (source, Some(state.active_window_id()?));
+        windows.contains(key).then(|| windows.find(key))
+    });
+    for (root, mut tabs) in merged {
+        // Order, bounds and the selected tab come from the window holding
+        // most of the copies kept, the newest one on ties.
+        let primary = windows
+            .members(root)
+            .into_iter()
+            .max_by_key(|&key| {
+                let kept = tabs
+                    .iter()
+                    .filter(|tab_copies| newest(tab_copies).window() == key)
+                    .count();
+                (kept, key.0, std::cmp::Reverse(key.1))
+            })
+            .unwrap_or(root);
+        tabs.sort_by_key(|tab_copies| {
+            let chosen = newest(tab_copies);
+            match tab_copies.iter().find(|copy| copy.window() == primary) {
+                Some(copy) => (false, 0, copy.tab.index, copy.tab.id),
+                None => (true, chosen.source, chosen.tab.index, chosen.tab.id),
+            }
+        });
+
+        let window_id = next_id;
+        next_id += 1;
+        if active == Some(root) {
+            active_window = Some(window_id);
+        }
+        let window = match primary {
+            (source, Some(id)) => states[source].window(id).cloned(),
+            (_, None) => None,
+        }
+        .unwrap_or_else(|| Window::new(window_id, 0));
+        let selected = window.selected_tab_index.and_then(|selected| {
+            tabs.iter().position(|tab_copies| {
+                tab_copies
+                    .iter()
+                    .any(|copy| copy.window() == primary && copy.tab.index == Some(selected))
+            })
+        });
+
+        let mut renumbered = Vec::new();
+        for tab_copies in &tabs {
+            let chosen = newest(tab_copies);
+            if let Some(group) = chosen.tab.group {
+                let metadata = states.iter().rev().find_map(|state| state.group(group));
+                groups.entry(group).or_insert(metadata);
+            }
+            renumbered.push((next_id, chosen.tab));
+            next_id += 1;
+        }
+        push_window_commands(window_id, &window, &renumbered, selected, &mut commands);
+    }
+
+    let mut state = SessionState::new(SnssFileType::Session);
+    for group in groups.into_values().flatten() {
+        state.apply(0, group_metadata_command(group));
+    }
+    for command in commands {
+        state.apply(0, command);
+    }
+    if let Some(id) = active_window {
+        state.apply(0, SessionCommand::ActiveWindow(id));
+    }
+    state
+}
+
+/// Groups the restorable tabs of all session states into copies of the
+/// same tab, in the order they were first seen.
+fn collect_copies<'a>(states: &[&'a SessionState]) -> Vec<Vec<TabCopy<'a>>> {
+    let mut copies: Vec<Vec<TabCopy>> = Vec::new();
+    let mut by_guid: HashMap<&str, usize> = HashMap::new();
+    let mut by_id: HashMap<i32, Vec<usize>> = HashMap::new();
+    for (source, state) in states.iter().enumerate() {
+        if state.file_type() != SnssFileType::Session {
+            continue;
+        }
+        let open = state.tabs().filter(|tab| {
+            tab.closed.is_none()
+                && !tab.navigations.is_empty()
+                && tab
+                    .window_id
+                    .and_then(|id| state.window(id))
+                    .is_none_or(|window| window.closed.is_none())
+        });
+        for tab in open {
+            let copy = TabCopy { source, tab };
+            let found = tab
+                .guid
+                .as_deref()
+                .and_then(|guid| by_guid.get(guid).copied())
+                .or_else(|| {
+                    by_id.get(&tab.id)?.iter().copied().find(|&index| {
+                        copies[index]
+                            .iter()
+                            .all(|other| is_same_tab(other.tab, tab) && other.source != source)
+                    })
+                });
+            let index = match found {
+                Some(index) => {
+                    copies[index].push(copy);
+                    index
+                }
+                None => {
+                    copies.push(vec![copy]);
+                    copies.len() - 1
+                }
+            };
+            if let Some(guid) = &tab.guid {
+                by_guid.entry(guid).or_insert(index);
+            }
+            let ids = by_id.entry(tab.id).or_default();
+            if !ids.contains(&index) {
+                ids.push(index);
+            }
+        }
+    }
+    copies
+}
+
+/// The copy with the most recent activity; later files win ties.
+fn newest<'a>(copies: &[TabCopy<'a>]) -> TabCopy<'a> {
+    *copies
+        .iter()
+        .max_by_key(|copy| (copy.latest(), copy.source))
+        .expect("copies are never empty")
+}
+
+/// Windows joined by the tabs they share.
+#[derive(Default)]
+struct WindowSets {
+    parents: HashMap<WindowKey, WindowKey>,
+}
+
+impl WindowSets {
+    fn contains(&self, key: WindowKey) -> bool {
+        self.parents.contains_key(&key)
+    }
+
+    /// The smallest window of the set, so sets sort by their oldest window.
+    fn find(&self, mut key: WindowKey) -> WindowKey {
+        while let Some(&parent) = self.parents.get(&key) {
+            if parent == key {
+                break;
+            }
+            key = parent;
+        }
+        key
+    }
+
+    fn union(&mut self, a: WindowKey, b: WindowKey) {
+        self.parents.entry(a).or_insert(a);
+        self.parents.entry(b).or_insert(b);
+        let (a, b) = (self.find(a), self.find(b));
+        let (root, child) = if a <= b { (a, b) } else { (b, a) };
+        self.parents.insert(child, root);
+    }
+
+    fn members(&self, root: WindowKey) -> Vec<WindowKey> {
+        self.parents
+            .keys()
+            .copied()
+            .filter(|&key| self.find(key) == root)
+            .collect()
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::iterator::{PickleError, PickleIterator, String16};
+use crate::time::{ChromeTime, TimeTicks};
+
+/// Decodes a value from consecutive pickle fields.
+///
+/// Implement it by hand or with `#[derive(FromPickle)]`. The derive reads
+/// the fields of a struct in declaration order and supports:
+///
+/// * `Option<T>` fields, which must come last and read as `None` once the
+///   pickle is exhausted, for fields Chromium appended in later versions;
+/// * `Vec<T>` and `HashMap<K, V>`, prefixed by an `int` element count;
+/// * `#[pickle(string16)]` on `String` fields stored as `std::u16string`.
+pub trait FromPickle: Sized {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError>;
+}
+
+/// Encodes a value as pickle fields; the counterpart of [`FromPickle`].
+///
+/// The derive writes trailing `Option` fields up to the first `None`.
+pub trait ToPickle {
+    fn to_pickle(&self, pickle: &mut PickleWriter);
+}
+
+/// Builds a Chromium `base::Pickle`, padding every field to four bytes.
+#[derive(Debug, Default, Clone)]
+pub struct PickleWriter {
+    payload: Vec<u8>,
+}
+
+impl PickleWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends raw bytes followed by padding to the next field boundary.
+    pub fn write_aligned(&mut self, data: &[u8]) {
+        self.payload.extend_from_slice(data);
+        self.payload
+            .resize(self.payload.len().next_multiple_of(4), 0);
+    }
+
+    pub fn write_uint16(&mut self, value: u16) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    pub fn write_uint32(&mut self, value: u32) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    pub fn write_uint64(&mut self, value: u64) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    pub fn write_int16(&mut self, value: i16) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    pub fn write_int32(&mut self, value: i32) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    pub fn write_int64(&mut self, value: i64) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_int32(value as i32);
+    }
+
+    pub fn write_single(&mut self, value: f32) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    pub fn write_double(&mut self, value: f64) {
+        self.write_aligned(&value.to_le_bytes());
+    }
+
+    /// Writes a length-prefixed byte string, as used for `std::string`.
+    pub fn write_bytes(&mut self, value: &[u8]) {
+        self.write_uint32(value.len() as u32);
+        self.write_aligned(value);
+    }
+
+    pub fn write_string(&mut self, value: &str) {
+        self.write_bytes(value.as_bytes());
+    }
+
+    pub fn write_string16(&mut self, value: &str) {
+        let units: Vec<u16> = value.encode_utf16().collect();
+        self.write_utf16_units(&units);
+    }
+
+    pub fn write_utf16_units(&mut self, units: &[u16]) {
+        self.write_uint32(units.len() as u32);
+        let data: Vec<u8> = units.iter().flat_map(|unit| unit.to_le_bytes()).collect();
+        self.write_aligned(&data);
+    }
+
+    pub fn write_datetime(&mut self, value: ChromeTime) {
+        self.write_int64(value.internal_value());
+    }
+
+    pub fn write_time_ticks(&mut self, value: TimeTicks) {
+        self.write_int64(value.internal_value());
+    }
+
+    /// Size of the payload written so far, without the header.
+    pub fn len(&self) -> usize {
+        self.payload.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.payload.is_empty()
+    }
+
+    /// Returns the finished pickle: the payload size followed by the
+    /// payload, ready for [`PickleIterator::new`].
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut data = (self.payload.len() as u32).to_le_bytes().to_vec();
+        data.extend(self.payload);
+        data
+    }
+}
+
+macro_rules! primitive_pickle {
+    ($($ty:ty => $read:ident, $write:ident;)*) => {
+        $(
+            impl FromPickle for $ty {
+                fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+                    pickle.$read()
+                }
+            }
+
+            impl ToPickle for $ty {
+                fn to_pickle(&self, pickle: &mut PickleWriter) {
+                    pickle.$write(*self);
+                }
+            }
+        )*
+    };
+}
+
+primitive_pickle! {
+    u16 => read_uint16, write_uint16;
+    u32 => read_uint32, write_uint32;
+    u64 => read_uint64, write_uint64;
+    i16 => read_int16, write_int16;
+    i32 => read_int32, write_int32;
+    i64 => read_int64, write_int64;
+    bool => read_bool, write_bool;
+    f32 => read_single, write_single;
+    f64 => read_double, write_double;
+    ChromeTime => read_datetime, write_datetime;
+    TimeTicks => read_time_ticks, write_time_ticks;
+}
+
+impl FromPickle for String {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        pickle.read_string()
+    }
+}
+
+impl ToPickle for String {
+    fn to_pickle(&self, pickle: &mut PickleWriter) {
+        pickle.write_string(self);
+    }
+}
+
+impl FromPickle for String16 {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        pickle.read_string16_lossy()
+    }
+}
+
+impl ToPickle for String16 {
+    fn to_pickle(&self, pickle: &mut PickleWriter) {
+        pickle.write_utf16_units(self.units());
+    }
+}
+
+/// A trailing field that older writers omit: `None` once the pickle is
+/// exhausted, otherwise the field must decode.
+impl<T: FromPickle> FromPickle for Option<T> {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        if pickle.remaining() == 0 {
+            return Ok(None);
+        }
+        T::from_pickle(pickle).map(Some)
+    }
+}
+
+/// Writes nothing for `None`, which only reads back correctly for the last
+/// field of a pickle.
+impl<T: ToPickle> ToPickle for Option<T> {
+    fn to_pickle(&self, pickle: &mut PickleWriter) {
+        if let Some(value) = self {
+            value.to_pickle(pickle);
+        }
+    }
+}
+
+fn read_count(pickle: &mut PickleIterator) -> Result<usize, PickleError> {
+    let count = pickle.read_int32()?;
+    usize::try_from(count).map_err(|_| PickleError::InvalidCount(count))
+}
+
+impl<T: FromPickle> FromPickle for Vec<T> {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        let count = read_count(pickle)?;
+        // Every element takes at least one aligned field.
+        let mut values = Vec::with_capacity(count.min(pickle.remaining() / 4));
+        for _ in 0..count {
+            values.push(T::from_pickle(pickle)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<T: ToPickle> ToPickle for Vec<T> {
+    fn to_pickle(&self, pickle: &mut PickleWriter) {
+        pickle.write_int32(self.len() as i32);
+        for value in self {
+            value.to_pickle(pickle);
+        }
+    }
+}
+
+impl<K: FromPickle + Eq + Hash, V: FromPickle> FromPickle for HashMap<K, V> {
+    fn from_pickle(pickle: &mut PickleIterator) -> Result<Self, PickleError> {
+        let count = read_count(pickle)?;
+        let mut map = HashMap::new();
+        for _ in 0..count {
+            let key = K::from_pickle(pickle)?;
+            let value = V::from_pickle(pickle)?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+}
+
+/// Entries are written in key order, as Chromium does for its `std::map`s,
+/// so the output is deterministic.
+impl<K: ToPickle + Ord, V: ToPickle> ToPickle for HashMap<K, V> {
+    fn to_pickle(&self, pickle: &mut PickleWriter) {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        pickle.write_int32(entries.len() as i32);
+        for (key, value) in entries {
+            key.to_pickle(pickle);
+            value.to_pickle(pickle);
+        }
+    }
+}
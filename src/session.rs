@@ -0,0 +1,575 @@
+//! Rebuilds the windows, tabs and closed entries a file describes by
+//! replaying its commands, much like Chromium does on restore.
+//!
+//! Unlike Chromium, closed tabs and windows of a session file are kept and
+//! marked with their closing time, since they are often what an
+//! investigation is after.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use crate::time::ChromeTime;
+use crate::{
+    ClosedWindow, IdAndIndex, IdAndTime, NavigationEntry, PinnedState, SelectedNavigationInTab,
+    SessionCommand, SnssError, SnssFile, SnssFileType, SnssVersion, SnssWriter, TabGroupColor,
+    TabGroupId, TabGroupMembership, TabGroupMetadata, TabGuid, TabWindow, WindowBounds,
+};
+
+/// A navigation and the offset of the command that last wrote it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Navigation {
+    pub offset: u64,
+    pub entry: NavigationEntry,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub id: i32,
+    /// Offset of the first command that mentioned the window.
+    pub offset: u64,
+    /// Chromium's `SessionWindow::WindowType`; 0 is a normal window.
+    pub window_type: Option<i32>,
+    pub bounds: Option<WindowBounds>,
+    pub selected_tab_index: Option<i32>,
+    pub closed: Option<ChromeTime>,
+}
+
+impl Window {
+    pub(crate) fn new(id: i32, offset: u64) -> Self {
+        Self {
+            id,
+            offset,
+            window_type: None,
+            bounds: None,
+            selected_tab_index: None,
+            closed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tab {
+    pub id: i32,
+    /// Offset of the first command that mentioned the tab.
+    pub offset: u64,
+    pub window_id: Option<i32>,
+    /// Position of the tab in its window's tab strip.
+    pub index: Option<i32>,
+    pub guid: Option<String>,
+    pub pinned: bool,
+    pub group: Option<TabGroupId>,
+    pub selected_navigation_index: Option<i32>,
+    pub last_active: Option<ChromeTime>,
+    pub closed: Option<ChromeTime>,
+    /// The back/forward list ordered by navigation index.
+    pub navigations: Vec<Navigation>,
+}
+
+impl Tab {
+    fn new(id: i32, offset: u64) -> Self {
+        Self {
+            id,
+            offset,
+            window_id: None,
+            index: None,
+            guid: None,
+            pinned: false,
+            group: None,
+            selected_navigation_index: None,
+            last_active: None,
+            closed: None,
+            navigations: Vec::new(),
+        }
+    }
+
+    /// The navigation the tab showed: the selected one, or the last one if
+    /// the selection is unknown or points past the list.
+    pub fn current_navigation(&self) -> Option<&Navigation> {
+        self.selected_navigation_index
+            .and_then(|index| {
+                self.navigations
+                    .iter()
+                    .find(|navigation| navigation.entry.index() == index)
+            })
+            .or_else(|| self.navigations.last())
+    }
+
+    fn update_navigation(&mut self, offset: u64, entry: NavigationEntry) {
+        let index = entry.index();
+        let navigation = Navigation { offset, entry };
+        match self
+            .navigations
+            .binary_search_by_key(&index, |navigation| navigation.entry.index())
+        {
+            Ok(position) => self.navigations[position] = navigation,
+            Err(position) => self.navigations.insert(position, navigation),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabGroup {
+    pub id: TabGroupId,
+    /// Offset of the command that last set the group's metadata.
+    pub offset: u64,
+    pub title: String,
+    pub color: TabGroupColor,
+}
+
+/// A tab or window from a tab-restore file that can still be reopened.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClosedEntry {
+    /// A tab closed on its own; [`Tab::closed`] holds the closing time.
+    Tab(Tab),
+    /// A window closed with its tabs; [`Window::closed`] holds the
+    /// closing time.
+    Window { window: Window, tabs: Vec<Tab> },
+}
+
+impl ClosedEntry {
+    pub fn id(&self) -> i32 {
+        match self {
+            ClosedEntry::Tab(tab) => tab.id,
+            ClosedEntry::Window { window, .. } => window.id,
+        }
+    }
+
+    pub fn offset(&self) -> u64 {
+        match self {
+            ClosedEntry::Tab(tab) => tab.offset,
+            ClosedEntry::Window { window, .. } => window.offset,
+        }
+    }
+
+    pub fn closed(&self) -> Option<ChromeTime> {
+        match self {
+            ClosedEntry::Tab(tab) => tab.closed,
+            ClosedEntry::Window { window, .. } => window.closed,
+        }
+    }
+}
+
+/// The state described by a session or tab-restore file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionState {
+    file_type: SnssFileType,
+    windows: BTreeMap<i32, Window>,
+    tabs: BTreeMap<i32, Tab>,
+    groups: BTreeMap<TabGroupId, TabGroup>,
+    active_window_id: Option<i32>,
+    closed_entries: Vec<ClosedEntry>,
+    /// Tabs of the closed window being read, still to come.
+    pending_window_tabs: i32,
+}
+
+impl SessionState {
+    pub fn new(file_type: SnssFileType) -> Self {
+        Self {
+            file_type,
+            windows: BTreeMap::new(),
+            tabs: BTreeMap::new(),
+            groups: BTreeMap::new(),
+            active_window_id: None,
+            closed_entries: Vec::new(),
+            pending_window_tabs: 0,
+        }
+    }
+
+    /// Replays every command of a file.
+    pub fn from_file(snss_file: &mut SnssFile) -> Result<Self, SnssError> {
+        let mut state = Self::new(snss_file.file_type());
+        for command in snss_file.iter_commands_with_offsets() {
+            let (offset, command) = command?;
+            state.apply(offset, command);
+        }
+        Ok(state)
+    }
+
+    pub fn file_type(&self) -> SnssFileType {
+        self.file_type
+    }
+
+    /// Applies one command read at `offset`.
+    pub fn apply(&mut self, offset: u64, command: SessionCommand) {
+        match self.file_type {
+            SnssFileType::Session => self.apply_session(offset, command),
+            SnssFileType::Tab => self.apply_tab_restore(offset, command),
+        }
+    }
+
+    fn window_mut(&mut self, id: i32, offset: u64) -> &mut Window {
+        self.windows
+            .entry(id)
+            .or_insert_with(|| Window::new(id, offset))
+    }
+
+    fn tab_mut(&mut self, id: i32, offset: u64) -> &mut Tab {
+        self.tabs.entry(id).or_insert_with(|| Tab::new(id, offset))
+    }
+
+    fn apply_session(&mut self, offset: u64, command: SessionCommand) {
+        match command {
+            SessionCommand::NavigationEntry(entry) => {
+                self.tab_mut(entry.session_id(), offset)
+                    .update_navigation(offset, *entry);
+            }
+            SessionCommand::TabWindow(tab_window) => {
+                self.window_mut(tab_window.window_id, offset);
+                self.tab_mut(tab_window.tab_id, offset).window_id = Some(tab_window.window_id);
+            }
+            SessionCommand::WindowBounds(bounds) => {
+                self.window_mut(bounds.window_id, offset).bounds = Some(bounds);
+            }
+            SessionCommand::TabIndexInWindow(payload) => {
+                self.tab_mut(payload.id, offset).index = Some(payload.index);
+            }
+            SessionCommand::SelectedNavigationIndex(payload) => {
+                self.tab_mut(payload.id, offset).selected_navigation_index = Some(payload.index);
+            }
+            SessionCommand::SelectedTabInIndex(payload) => {
+                self.window_mut(payload.id, offset).selected_tab_index = Some(payload.index);
+            }
+            SessionCommand::WindowType(payload) => {
+                self.window_mut(payload.id, offset).window_type = Some(payload.index);
+            }
+            SessionCommand::TabNavigationPathPrunedFromBack(payload) => {
+                let tab = self.tab_mut(payload.id, offset);
+                tab.navigations
+                    .retain(|navigation| navigation.entry.index() < payload.index);
+            }
+            SessionCommand::TabNavigationPathPrunedFromFront(payload) => {
+                let tab = self.tab_mut(payload.id, offset);
+                tab.navigations
+                    .retain(|navigation| navigation.entry.index() >= payload.index);
+                for navigation in &mut tab.navigations {
+                    let index = navigation.entry.index() - payload.index;
+                    navigation.entry.set_index(index);
+                }
+                if let Some(selected) = &mut tab.selected_navigation_index {
+                    *selected = (*selected - payload.index).max(0);
+                }
+            }
+            SessionCommand::TabNavigationPathPruned(payload) => {
+                // Chromium ignores payloads that remove nothing.
+                if payload.index < 0 || payload.count <= 0 {
+                    return;
+                }
+                let end = payload.index.saturating_add(payload.count);
+                let tab = self.tab_mut(payload.id, offset);
+                tab.navigations
+                    .retain(|navigation| !(payload.index..end).contains(&navigation.entry.index()));
+                for navigation in &mut tab.navigations {
+                    let index = navigation.entry.index();
+                    if index >= end {
+                        navigation.entry.set_index(index - payload.count);
+                    }
+                }
+                if let Some(selected) = &mut tab.selected_navigation_index {
+                    if *selected >= end {
+                        *selected -= payload.count;
+                    } else if *selected >= payload.index {
+                        *selected = (payload.index - 1).max(0);
+                    }
+                }
+            }
+            SessionCommand::PinnedState(payload) => {
+                self.tab_mut(payload.tab_id, offset).pinned = payload.pinned;
+            }
+            SessionCommand::TabClosed(payload) => {
+                self.tab_mut(payload.id, offset).closed = Some(payload.time);
+            }
+            SessionCommand::WindowClosed(payload) => {
+                self.window_mut(payload.id, offset).closed = Some(payload.time);
+            }
+            SessionCommand::ActiveWindow(id) => self.active_window_id = Some(id),
+            SessionCommand::LastActiveTime(payload) => {
+                self.tab_mut(payload.id, offset).last_active = Some(payload.time);
+            }
+            SessionCommand::TabGroup(payload) => {
+                self.tab_mut(payload.tab_id, offset).group = payload.group;
+            }
+            SessionCommand::TabGroupMetadata(metadata) => {
+                self.groups.insert(
+                    metadata.group,
+                    TabGroup {
+                        id: metadata.group,
+                        offset,
+                        title: metadata.title.into_string(),
+                        color: metadata.color,
+                    },
+                );
+            }
+            SessionCommand::TabGuid(payload) => {
+                self.tab_mut(payload.tab_id, offset).guid = Some(payload.guid);
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_tab_restore(&mut self, offset: u64, command: SessionCommand) {
+        match command {
+            SessionCommand::ClosedWindow(ClosedWindow {
+                id,
+                selected_tab_index,
+                num_tabs,
+                timestamp,
+            }) => {
+                let mut window = Window::new(id, offset);
+                window.selected_tab_index = Some(selected_tab_index);
+                window.closed = Some(timestamp);
+                self.closed_entries.push(ClosedEntry::Window {
+                    window,
+                    tabs: Vec::new(),
+                });
+                self.pending_window_tabs = num_tabs;
+            }
+            SessionCommand::SelectedNavigationInTab(SelectedNavigationInTab {
+                id,
+                index,
+                timestamp,
+            }) => {
+                let mut tab = Tab::new(id, offset);
+                tab.selected_navigation_index = Some(index);
+                tab.closed = Some(timestamp);
+                match self.closed_entries.last_mut() {
+                    Some(ClosedEntry::Window { window, tabs }) if self.pending_window_tabs > 0 => {
+                        tab.window_id = Some(window.id);
+                        tab.index = Some(tabs.len() as i32);
+                        tabs.push(tab);
+                        self.pending_window_tabs -= 1;
+                    }
+                    _ => {
+                        self.pending_window_tabs = 0;
+                        self.closed_entries.push(ClosedEntry::Tab(tab));
+                    }
+                }
+            }
+            SessionCommand::NavigationEntry(entry) => {
+                if let Some(tab) = self.current_closed_tab(entry.session_id()) {
+                    tab.update_navigation(offset, *entry);
+                }
+            }
+            SessionCommand::EntryPinned(pinned) => {
+                if let Some(tab) = self.last_closed_tab() {
+                    tab.pinned = pinned;
+                }
+            }
+            SessionCommand::RestoredEntry(id) => {
+                self.closed_entries.retain(|entry| entry.id() != id);
+            }
+            _ => {}
+        }
+    }
+
+    fn last_closed_tab(&mut self) -> Option<&mut Tab> {
+        match self.closed_entries.last_mut()? {
+            ClosedEntry::Tab(tab) => Some(tab),
+            ClosedEntry::Window { tabs, .. } => tabs.last_mut(),
+        }
+    }
+
+    fn current_closed_tab(&mut self, id: i32) -> Option<&mut Tab> {
+        self.last_closed_tab().filter(|tab| tab.id == id)
+    }
+
+    /// All windows, ordered by id, including closed ones.
+    pub fn windows(&self) -> impl Iterator<Item = &Window> {
+        self.windows.values()
+    }
+
+    pub fn window(&self, id: i32) -> Option<&Window> {
+        self.windows.get(&id)
+    }
+
+    /// All tabs, ordered by id, including closed ones.
+    pub fn tabs(&self) -> impl Iterator<Item = &Tab> {
+        self.tabs.values()
+    }
+
+    pub fn tab(&self, id: i32) -> Option<&Tab> {
+        self.tabs.get(&id)
+    }
+
+    /// The tabs of a window in tab strip order.
+    pub fn tabs_in_window(&self, window_id: i32) -> Vec<&Tab> {
+        let mut tabs: Vec<&Tab> = self
+            .tabs
+            .values()
+            .filter(|tab| tab.window_id == Some(window_id))
+            .collect();
+        tabs.sort_by_key(|tab| (tab.index.unwrap_or(i32::MAX), tab.id));
+        tabs
+    }
+
+    pub fn groups(&self) -> impl Iterator<Item = &TabGroup> {
+        self.groups.values()
+    }
+
+    pub fn group(&self, id: TabGroupId) -> Option<&TabGroup> {
+        self.groups.get(&id)
+    }
+
+    pub fn active_window_id(&self) -> Option<i32> {
+        self.active_window_id
+    }
+
+    /// Entries of a tab-restore file that were not restored, in file
+    /// order. Always empty for session files; see [`Tab::closed`] and
+    /// [`Window::closed`] there.
+    pub fn closed_entries(&self) -> &[ClosedEntry] {
+        &self.closed_entries
+    }
+
+    /// Commands that recreate the open windows and tabs, as Chromium writes
+    /// them when it starts a new session file. Closed tabs and windows are
+    /// left out, so this is empty for tab-restore files.
+    pub fn to_session_commands(&self) -> Vec<SessionCommand> {
+        let mut windows = Vec::new();
+        let mut groups = BTreeSet::new();
+        for window in self.windows().filter(|window| window.closed.is_none()) {
+            let tabs: Vec<(i32, &Tab)> = self
+                .tabs_in_window(window.id)
+                .into_iter()
+                .filter(|tab| tab.closed.is_none())
+                .map(|tab| (tab.id, tab))
+                .collect();
+            if tabs.is_empty() {
+                continue;
+            }
+            groups.extend(tabs.iter().filter_map(|(_, tab)| tab.group));
+            let selected = tabs
+                .iter()
+                .position(|(_, tab)| tab.index.is_some() && tab.index == window.selected_tab_index);
+            windows.push((window, tabs, selected));
+        }
+
+        let mut commands: Vec<SessionCommand> = groups
+            .into_iter()
+            .filter_map(|id| self.group(id))
+            .map(group_metadata_command)
+            .collect();
+        for (window, tabs, selected) in &windows {
+            push_window_commands(window.id, window, tabs, *selected, &mut commands);
+        }
+        if let Some(id) = self
+            .active_window_id
+            .filter(|id| windows.iter().any(|(window, _, _)| window.id == *id))
+        {
+            commands.push(SessionCommand::ActiveWindow(id));
+        }
+        commands
+    }
+
+    /// Writes a version 3 session file holding
+    /// [`SessionState::to_session_commands`].
+    pub fn write_session<W: Write>(&self, out: W) -> Result<(), SnssError> {
+        write_session_commands(&self.to_session_commands(), out)
+    }
+}
+
+/// Whether two tabs with the same session id, from different files, are
+/// the same tab: their GUIDs do not disagree and they share a navigation.
+/// Session ids restart with every browser run, so the id alone is not
+/// enough.
+pub(crate) fn is_same_tab(a: &Tab, b: &Tab) -> bool {
+    if let (Some(a), Some(b)) = (&a.guid, &b.guid)
+        && a != b
+    {
+        return false;
+    }
+    a.navigations.iter().any(|x| {
+        b.navigations
+            .iter()
+            .any(|y| x.entry.url() == y.entry.url() && x.entry.timestamp() == y.entry.timestamp())
+    })
+}
+
+pub(crate) fn write_session_commands<W: Write>(
+    commands: &[SessionCommand],
+    out: W,
+) -> Result<(), SnssError> {
+    let mut writer = SnssWriter::new(SnssFileType::Session, SnssVersion::V3, out)?;
+    for command in commands {
+        writer.write_command(command)?;
+    }
+    writer.write_command(&SessionCommand::InitialStateMarker)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub(crate) fn group_metadata_command(group: &TabGroup) -> SessionCommand {
+    SessionCommand::TabGroupMetadata(TabGroupMetadata {
+        group: group.id,
+        title: group.title.as_str().into(),
+        color: group.color,
+    })
+}
+
+/// Pushes the commands of an open window, renumbered to `window_id`, whose
+/// tabs are given in tab strip order with their new ids. `selected` is the
+/// position of the selected tab in `tabs`.
+pub(crate) fn push_window_commands(
+    window_id: i32,
+    window: &Window,
+    tabs: &[(i32, &Tab)],
+    selected: Option<usize>,
+    commands: &mut Vec<SessionCommand>,
+) {
+    if let Some(bounds) = window.bounds {
+        commands.push(SessionCommand::WindowBounds(WindowBounds {
+            window_id,
+            ..bounds
+        }));
+    }
+    commands.push(SessionCommand::WindowType(IdAndIndex {
+        id: window_id,
+        index: window.window_type.unwrap_or_default(),
+    }));
+    for (index, (tab_id, tab)) in tabs.iter().enumerate() {
+        let tab_id = *tab_id;
+        commands.push(SessionCommand::TabWindow(TabWindow { window_id, tab_id }));
+        commands.push(SessionCommand::TabIndexInWindow(IdAndIndex {
+            id: tab_id,
+            index: index as i32,
+        }));
+        if let Some(guid) = &tab.guid {
+            commands.push(SessionCommand::TabGuid(TabGuid {
+                tab_id,
+                guid: guid.clone(),
+            }));
+        }
+        if tab.pinned {
+            commands.push(SessionCommand::PinnedState(PinnedState {
+                tab_id,
+                pinned: true,
+            }));
+        }
+        if tab.group.is_some() {
+            commands.push(SessionCommand::TabGroup(TabGroupMembership {
+                tab_id,
+                group: tab.group,
+            }));
+        }
+        if let Some(time) = tab.last_active {
+            commands.push(SessionCommand::LastActiveTime(IdAndTime {
+                id: tab_id,
+                time,
+            }));
+        }
+        for navigation in &tab.navigations {
+            let mut entry = navigation.entry.clone();
+            entry.set_session_id(tab_id);
+            commands.push(SessionCommand::NavigationEntry(Box::new(entry)));
+        }
+        if let Some(index) = tab.selected_navigation_index {
+            commands.push(SessionCommand::SelectedNavigationIndex(IdAndIndex {
+                id: tab_id,
+                index,
+            }));
+        }
+    }
+    commands.push(SessionCommand::SelectedTabInIndex(IdAndIndex {
+        id: window_id,
+        index: selected.unwrap_or_default() as i32,
+    }));
+}
@@ -0,0 +1,207 @@
+//! The navigation task tree of a session.
+//!
+//! Chromium gives every navigation a task id and records the task it was
+//! started from, e.g. the page a link was clicked on, even when the link
+//! opened in another tab. The ids are carried in each [`NavigationEntry`],
+//! so the tree can be rebuilt across all tabs of a session file.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+use crate::time::ChromeTime;
+use crate::{NavigationEntry, SessionCommand, SnssError, SnssFile};
+
+/// One navigation in the task tree.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskNode {
+    pub task_id: i64,
+    pub parent_task_id: Option<i64>,
+    pub root_task_id: Option<i64>,
+    /// Tab the navigation belongs to.
+    pub tab_id: i32,
+    /// Position of the navigation in the tab's back/forward list.
+    pub index: i32,
+    pub url: String,
+    pub title: String,
+    pub timestamp: ChromeTime,
+}
+
+/// Chromium uses -1 for unset task ids; older entries hold zero.
+fn task_id(id: Option<i64>) -> Option<i64> {
+    id.filter(|id| *id > 0)
+}
+
+impl TaskNode {
+    /// `None` for entries without a task id.
+    pub fn from_entry(entry: &NavigationEntry) -> Option<Self> {
+        Some(TaskNode {
+            task_id: task_id(entry.task_id())?,
+            parent_task_id: task_id(entry.parent_task_id()),
+            root_task_id: task_id(entry.root_task_id()),
+            tab_id: entry.session_id(),
+            index: entry.index(),
+            url: entry.url().to_string(),
+            title: entry.title().to_string(),
+            timestamp: entry.timestamp(),
+        })
+    }
+}
+
+/// Navigations linked by their parent task ids.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskGraph {
+    nodes: BTreeMap<i64, TaskNode>,
+}
+
+#[derive(Serialize)]
+struct Edge {
+    parent: i64,
+    child: i64,
+}
+
+#[derive(Serialize)]
+struct JsonGraph<'a> {
+    nodes: Vec<&'a TaskNode>,
+    edges: Vec<Edge>,
+}
+
+impl TaskGraph {
+    /// Builds the graph from navigations in file order. Session files
+    /// rewrite an entry whenever it changes, so a later entry with the
+    /// same task id replaces the earlier one.
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a NavigationEntry>) -> Self {
+        let nodes = entries
+            .into_iter()
+            .filter_map(TaskNode::from_entry)
+            .map(|node| (node.task_id, node))
+            .collect();
+        TaskGraph { nodes }
+    }
+
+    /// Builds the graph from every navigation in a file.
+    pub fn from_file(snss_file: &mut SnssFile) -> Result<Self, SnssError> {
+        let mut entries = Vec::new();
+        for command in snss_file.iter_session_commands() {
+            if let SessionCommand::NavigationEntry(entry) = command? {
+                entries.push(*entry);
+            }
+        }
+        Ok(Self::from_entries(&entries))
+    }
+
+    /// Nodes ordered by task id.
+    pub fn nodes(&self) -> impl Iterator<Item = &TaskNode> {
+        self.nodes.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn get(&self, task_id: i64) -> Option<&TaskNode> {
+        self.nodes.get(&task_id)
+    }
+
+    /// The navigation a task was started from, if it is in the graph.
+    pub fn parent(&self, task_id: i64) -> Option<&TaskNode> {
+        self.get(task_id)?
+            .parent_task_id
+            .and_then(|parent| self.get(parent))
+    }
+
+    pub fn children(&self, task_id: i64) -> impl Iterator<Item = &TaskNode> {
+        self.nodes
+            .values()
+            .filter(move |node| node.parent_task_id == Some(task_id))
+    }
+
+    /// Nodes whose parent is unset or missing from the graph.
+    pub fn roots(&self) -> impl Iterator<Item = &TaskNode> {
+        self.nodes.values().filter(|node| {
+            node.parent_task_id
+                .is_none_or(|parent| !self.nodes.contains_key(&parent))
+        })
+    }
+
+    /// Every navigation that descends from the same root task, the root
+    /// included.
+    pub fn tree(&self, root_task_id: i64) -> impl Iterator<Item = &TaskNode> {
+        self.nodes.values().filter(move |node| {
+            node.task_id == root_task_id || node.root_task_id == Some(root_task_id)
+        })
+    }
+
+    /// Parent-to-child links between nodes that are both in the graph.
+    pub fn edges(&self) -> impl Iterator<Item = (&TaskNode, &TaskNode)> {
+        self.nodes.values().filter_map(|child| {
+            let parent = self.get(child.parent_task_id?)?;
+            Some((parent, child))
+        })
+    }
+
+    /// Maps each tab to the tab it was opened from, found through a
+    /// navigation whose parent task lives in another tab.
+    pub fn tab_openers(&self) -> BTreeMap<i32, i32> {
+        let mut openers = BTreeMap::new();
+        for (parent, child) in self.edges() {
+            if parent.tab_id != child.tab_id {
+                openers.entry(child.tab_id).or_insert(parent.tab_id);
+            }
+        }
+        openers
+    }
+
+    /// Writes the graph in Graphviz DOT, one cluster per tab.
+    pub fn write_dot<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut tabs: BTreeMap<i32, Vec<&TaskNode>> = BTreeMap::new();
+        for node in self.nodes.values() {
+            tabs.entry(node.tab_id).or_default().push(node);
+        }
+
+        writeln!(out, "digraph tasks {{")?;
+        writeln!(out, "  node [shape=box];")?;
+        for (tab_id, nodes) in tabs {
+            writeln!(out, "  subgraph cluster_tab_{} {{", tab_id)?;
+            writeln!(out, "    label=\"tab {}\";", tab_id)?;
+            for node in nodes {
+                writeln!(
+                    out,
+                    "    t{} [label=\"{}\\n{}\"];",
+                    node.task_id,
+                    dot_escape(&node.title),
+                    dot_escape(&node.url)
+                )?;
+            }
+            writeln!(out, "  }}")?;
+        }
+        for (parent, child) in self.edges() {
+            writeln!(out, "  t{} -> t{};", parent.task_id, child.task_id)?;
+        }
+        writeln!(out, "}}")
+    }
+
+    /// Writes the graph as a JSON object with `nodes` and `edges` arrays.
+    pub fn write_json<W: Write>(&self, out: W) -> io::Result<()> {
+        let graph = JsonGraph {
+            nodes: self.nodes.values().collect(),
+            edges: self
+                .edges()
+                .map(|(parent, child)| Edge {
+                    parent: parent.task_id,
+                    child: child.task_id,
+                })
+                .collect(),
+        };
+        serde_json::to_writer_pretty(out, &graph).map_err(io::Error::from)
+    }
+}
+
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
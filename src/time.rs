@@ -0,0 +1,211 @@
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Microseconds from 1601-01-01, the epoch of Chromium's `base::Time`, to
+/// the Unix epoch.
+const UNIX_EPOCH_OFFSET_MICROS: i64 = 11_644_473_600_000_000;
+
+/// A wall-clock `base::Time` as Chromium serializes it: microseconds since
+/// 1601-01-01 00:00:00 UTC (the Windows/WebKit epoch).
+///
+/// A raw value of zero is Chromium's null time and usually means the field
+/// was never set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ChromeTime(i64);
+
+impl ChromeTime {
+    pub fn from_internal(value: i64) -> Self {
+        ChromeTime(value)
+    }
+
+    /// The raw value as stored on disk.
+    pub fn internal_value(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_null(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn from_unix_micros(micros: i64) -> Self {
+        ChromeTime(micros.saturating_add(UNIX_EPOCH_OFFSET_MICROS))
+    }
+
+    /// Microseconds since 1970-01-01 00:00:00 UTC, negative before it.
+    pub fn unix_micros(self) -> i64 {
+        self.0.saturating_sub(UNIX_EPOCH_OFFSET_MICROS)
+    }
+
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let micros = match time.duration_since(UNIX_EPOCH) {
+            Ok(after) => i64::try_from(after.as_micros()).unwrap_or(i64::MAX),
+            Err(before) => i64::try_from(before.duration().as_micros())
+                .map(|micros| -micros)
+                .unwrap_or(i64::MIN),
+        };
+        Self::from_unix_micros(micros)
+    }
+
+    pub fn to_system_time(self) -> SystemTime {
+        let micros = self.unix_micros();
+        let magnitude = Duration::from_micros(micros.unsigned_abs());
+        if micros >= 0 {
+            UNIX_EPOCH + magnitude
+        } else {
+            UNIX_EPOCH - magnitude
+        }
+    }
+}
+
+impl From<ChromeTime> for SystemTime {
+    fn from(time: ChromeTime) -> Self {
+        time.to_system_time()
+    }
+}
+
+impl From<SystemTime> for ChromeTime {
+    fn from(time: SystemTime) -> Self {
+        ChromeTime::from_system_time(time)
+    }
+}
+
+/// Formats as an RFC 3339 UTC timestamp with microsecond precision.
+impl fmt::Display for ChromeTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let micros = self.unix_micros();
+        let secs = micros.div_euclid(1_000_000);
+        let subsec_micros = micros.rem_euclid(1_000_000);
+        let days = secs.div_euclid(86_400);
+        let secs_of_day = secs.rem_euclid(86_400);
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60,
+            subsec_micros
+        )
+    }
+}
+
+/// Serializes as the RFC 3339 string of [`fmt::Display`].
+impl Serialize for ChromeTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A monotonic `base::TimeTicks`: microseconds since an unspecified origin,
+/// normally system boot. It only orders events within one browser run and
+/// cannot be placed on the calendar without a known reference point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct TimeTicks(i64);
+
+impl TimeTicks {
+    pub fn from_internal(value: i64) -> Self {
+        TimeTicks(value)
+    }
+
+    pub fn internal_value(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_null(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Time elapsed since the clock's origin; `None` for negative values.
+    pub fn since_origin(self) -> Option<Duration> {
+        u64::try_from(self.0).ok().map(Duration::from_micros)
+    }
+
+    /// Places the tick count on the calendar given the wall-clock time of
+    /// the clock's origin.
+    pub fn to_chrome_time(self, origin: ChromeTime) -> ChromeTime {
+        ChromeTime(origin.0.saturating_add(self.0))
+    }
+}
+
+/// Converts days since 1970-01-01 into a proleptic Gregorian date.
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_epoch_is_null() {
+        let time = ChromeTime::from_internal(0);
+        assert!(time.is_null());
+        assert_eq!(time.to_string(), "1601-01-01T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn unix_epoch() {
+        let time = ChromeTime::from_internal(11_644_473_600_000_000);
+        assert_eq!(time.unix_micros(), 0);
+        assert_eq!(time.to_system_time(), UNIX_EPOCH);
+        assert_eq!(time.to_string(), "1970-01-01T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn known_session_timestamp() {
+        let time = ChromeTime::from_internal(13_345_678_901_234_567);
+        assert_eq!(time.to_string(), "2023-11-28T21:01:41.234567Z");
+        assert_eq!(
+            time.to_system_time(),
+            UNIX_EPOCH + Duration::from_micros(1_701_205_301_234_567)
+        );
+    }
+
+    #[test]
+    fn leap_day() {
+        let time = ChromeTime::from_unix_micros(951_782_400_000_000);
+        assert_eq!(time.to_string(), "2000-02-29T00:00:00.000000Z");
+    }
+
+    #[test]
+    fn before_unix_epoch() {
+        let time = ChromeTime::from_unix_micros(-1);
+        assert_eq!(time.to_string(), "1969-12-31T23:59:59.999999Z");
+        assert_eq!(time.to_system_time(), UNIX_EPOCH - Duration::from_micros(1));
+    }
+
+    #[test]
+    fn system_time_round_trip() {
+        for micros in [-86_400_000_000, 0, 1_701_205_301_234_567] {
+            let time = ChromeTime::from_unix_micros(micros);
+            assert_eq!(ChromeTime::from_system_time(time.to_system_time()), time);
+        }
+    }
+
+    #[test]
+    fn time_ticks() {
+        let ticks = TimeTicks::from_internal(5_000_000);
+        assert_eq!(ticks.since_origin(), Some(Duration::from_secs(5)));
+        assert_eq!(TimeTicks::from_internal(-1).since_origin(), None);
+
+        let boot = ChromeTime::from_unix_micros(0);
+        assert_eq!(
+            ticks.to_chrome_time(boot),
+            ChromeTime::from_unix_micros(5_000_000)
+        );
+    }
+}
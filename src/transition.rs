@@ -0,0 +1,406 @@
+use bitflags::bitflags;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Low byte of a `ui::PageTransition`: how the navigation was initiated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoreTransition {
+    Link,
+    Typed,
+    AutoBookmark,
+    AutoSubframe,
+    ManualSubframe,
+    Generated,
+    AutoToplevel,
+    FormSubmit,
+    Reload,
+    Keyword,
+    KeywordGenerated,
+    /// A core value this crate does not know, kept as read.
+    Unknown(u32),
+}
+
+const CORE_MASK: u32 = 0xff;
+
+/// Drops Chromium's `PAGE_TRANSITION_` prefix, in any case.
+fn strip_prefix(name: &str) -> &str {
+    const PREFIX: &str = "PAGE_TRANSITION_";
+    match name.get(..PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(PREFIX) => &name[PREFIX.len()..],
+        _ => name,
+    }
+}
+
+const CORE_NAMES: [(CoreTransition, &str); 11] = [
+    (CoreTransition::Link, "LINK"),
+    (CoreTransition::Typed, "TYPED"),
+    (CoreTransition::AutoBookmark, "AUTO_BOOKMARK"),
+    (CoreTransition::AutoSubframe, "AUTO_SUBFRAME"),
+    (CoreTransition::ManualSubframe, "MANUAL_SUBFRAME"),
+    (CoreTransition::Generated, "GENERATED"),
+    (CoreTransition::AutoToplevel, "AUTO_TOPLEVEL"),
+    (CoreTransition::FormSubmit, "FORM_SUBMIT"),
+    (CoreTransition::Reload, "RELOAD"),
+    (CoreTransition::Keyword, "KEYWORD"),
+    (CoreTransition::KeywordGenerated, "KEYWORD_GENERATED"),
+];
+
+impl CoreTransition {
+    pub fn from_u32(value: u32) -> Self {
+        match value & CORE_MASK {
+            0 => CoreTransition::Link,
+            1 => CoreTransition::Typed,
+            2 => CoreTransition::AutoBookmark,
+            3 => CoreTransition::AutoSubframe,
+            4 => CoreTransition::ManualSubframe,
+            5 => CoreTransition::Generated,
+            6 => CoreTransition::AutoToplevel,
+            7 => CoreTransition::FormSubmit,
+            8 => CoreTransition::Reload,
+            9 => CoreTransition::Keyword,
+            10 => CoreTransition::KeywordGenerated,
+            unknown => CoreTransition::Unknown(unknown),
+        }
+    }
+
+    pub fn as_u32(self) -> u32 {
+        match self {
+            CoreTransition::Unknown(value) => value,
+            known => CORE_NAMES
+                .iter()
+                .position(|(core, _)| *core == known)
+                .unwrap() as u32,
+        }
+    }
+
+    /// Chromium's name without the `PAGE_TRANSITION_` prefix, e.g.
+    /// `AUTO_BOOKMARK`.
+    pub fn chromium_name(self) -> Option<&'static str> {
+        CORE_NAMES
+            .iter()
+            .find(|(core, _)| *core == self)
+            .map(|(_, name)| *name)
+    }
+
+    pub fn from_chromium_name(name: &str) -> Option<Self> {
+        let name = strip_prefix(name);
+        CORE_NAMES
+            .iter()
+            .find(|(_, known)| known.eq_ignore_ascii_case(name))
+            .map(|(core, _)| *core)
+    }
+}
+
+impl fmt::Display for CoreTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match *self {
+            CoreTransition::Link => "Link",
+            CoreTransition::Typed => "Typed",
+            CoreTransition::AutoBookmark => "AutoBookmark",
+            CoreTransition::AutoSubframe => "AutoSubframe",
+            CoreTransition::ManualSubframe => "ManualSubframe",
+            CoreTransition::Generated => "Generated",
+            CoreTransition::AutoToplevel => "AutoToplevel",
+            CoreTransition::FormSubmit => "FormSubmit",
+            CoreTransition::Reload => "Reload",
+            CoreTransition::Keyword => "Keyword",
+            CoreTransition::KeywordGenerated => "KeywordGenerated",
+            CoreTransition::Unknown(value) => return write!(f, "Unknown({})", value),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+bitflags! {
+    /// High bits of a `ui::PageTransition` that qualify the core type.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Qualifier: u32 {
+        const Blocked = 0x00800000;
+        const ForwardBack = 0x01000000;
+        const FromAddressBar = 0x02000000;
+        const HomePage = 0x04000000;
+        const FromApi = 0x08000000;
+        const ChainStart = 0x10000000;
+        const ChainEnd = 0x20000000;
+        const ClientRedirect = 0x40000000;
+        const ServerRedirect = 0x80000000;
+    }
+}
+
+impl Qualifier {
+    /// `PAGE_TRANSITION_IS_REDIRECT_MASK`. Not a flag of its own, so it
+    /// stays out of `Debug` output and flag iteration.
+    pub const IS_REDIRECT_MASK: Qualifier =
+        Qualifier::ClientRedirect.union(Qualifier::ServerRedirect);
+}
+
+const QUALIFIER_MASK: u32 = 0xFFFFFF00;
+
+const QUALIFIER_NAMES: [(Qualifier, &str); 9] = [
+    (Qualifier::Blocked, "BLOCKED"),
+    (Qualifier::ForwardBack, "FORWARD_BACK"),
+    (Qualifier::FromAddressBar, "FROM_ADDRESS_BAR"),
+    (Qualifier::HomePage, "HOME_PAGE"),
+    (Qualifier::FromApi, "FROM_API"),
+    (Qualifier::ChainStart, "CHAIN_START"),
+    (Qualifier::ChainEnd, "CHAIN_END"),
+    (Qualifier::ClientRedirect, "CLIENT_REDIRECT"),
+    (Qualifier::ServerRedirect, "SERVER_REDIRECT"),
+];
+
+impl Qualifier {
+    pub fn from_u32(value: u32) -> Self {
+        Qualifier::from_bits_truncate(value & QUALIFIER_MASK)
+    }
+
+    /// Chromium's name of a single qualifier, e.g. `CHAIN_START`.
+    pub fn chromium_name(self) -> Option<&'static str> {
+        QUALIFIER_NAMES
+            .iter()
+            .find(|(qualifier, _)| *qualifier == self)
+            .map(|(_, name)| *name)
+    }
+
+    pub fn from_chromium_name(name: &str) -> Option<Self> {
+        let name = strip_prefix(name);
+        QUALIFIER_NAMES
+            .iter()
+            .find(|(_, known)| known.eq_ignore_ascii_case(name))
+            .map(|(qualifier, _)| *qualifier)
+    }
+}
+
+/// A `ui::PageTransition`: a core type in the low byte plus qualifier bits.
+///
+/// The raw value is kept, so unknown core types and qualifier bits survive
+/// a round trip through [`PageTransition::value`] and the string form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageTransition {
+    core_transition: CoreTransition,
+    qualifiers: Qualifier,
+    value: u32,
+}
+
+impl PageTransition {
+    pub fn new(value: u32) -> Self {
+        let core_transition = CoreTransition::from_u32(value);
+        let qualifiers = Qualifier::from_u32(value);
+
+        Self {
+            core_transition,
+            qualifiers,
+            value,
+        }
+    }
+
+    /// The raw value as stored on disk.
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    pub fn core(&self) -> CoreTransition {
+        self.core_transition
+    }
+
+    /// The known qualifiers; unknown bits are only kept in
+    /// [`PageTransition::value`].
+    pub fn qualifiers(&self) -> Qualifier {
+        self.qualifiers
+    }
+
+    pub fn core_type_is(&self, core: CoreTransition) -> bool {
+        self.core_transition == core
+    }
+
+    /// `ui::PageTransitionIsRedirect`.
+    pub fn is_redirect(&self) -> bool {
+        self.qualifiers.intersects(Qualifier::IS_REDIRECT_MASK)
+    }
+
+    pub fn is_client_redirect(&self) -> bool {
+        self.qualifiers.contains(Qualifier::ClientRedirect)
+    }
+
+    pub fn is_server_redirect(&self) -> bool {
+        self.qualifiers.contains(Qualifier::ServerRedirect)
+    }
+
+    /// `ui::PageTransitionIsMainFrame`: anything but a subframe navigation.
+    pub fn is_main_frame(&self) -> bool {
+        !matches!(
+            self.core_transition,
+            CoreTransition::AutoSubframe | CoreTransition::ManualSubframe
+        )
+    }
+
+    /// `ui::PageTransitionIsNewNavigation`: neither back/forward nor a
+    /// reload.
+    pub fn is_new_navigation(&self) -> bool {
+        !self.qualifiers.contains(Qualifier::ForwardBack)
+            && !self.core_type_is(CoreTransition::Reload)
+    }
+
+    /// `ui::PageTransitionIsWebTriggerable`: transitions a web page can
+    /// cause on its own.
+    pub fn is_web_triggerable(&self) -> bool {
+        matches!(
+            self.core_transition,
+            CoreTransition::Link
+                | CoreTransition::AutoSubframe
+                | CoreTransition::ManualSubframe
+                | CoreTransition::FormSubmit
+        )
+    }
+
+    pub fn is_chain_start(&self) -> bool {
+        self.qualifiers.contains(Qualifier::ChainStart)
+    }
+
+    pub fn is_chain_end(&self) -> bool {
+        self.qualifiers.contains(Qualifier::ChainEnd)
+    }
+
+    pub fn is_forward_back(&self) -> bool {
+        self.qualifiers.contains(Qualifier::ForwardBack)
+    }
+
+    pub fn is_from_address_bar(&self) -> bool {
+        self.qualifiers.contains(Qualifier::FromAddressBar)
+    }
+}
+
+impl From<u32> for PageTransition {
+    fn from(value: u32) -> Self {
+        PageTransition::new(value)
+    }
+}
+
+/// Formats as Chromium's names joined with `|`, e.g.
+/// `LINK|CHAIN_START|CHAIN_END`. Unknown core types are written as a
+/// number and unknown qualifier bits as a hexadecimal value.
+impl fmt::Display for PageTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.core_transition.chromium_name() {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "{}", self.value & CORE_MASK)?,
+        }
+        for (qualifier, name) in QUALIFIER_NAMES {
+            if self.qualifiers.contains(qualifier) {
+                write!(f, "|{}", name)?;
+            }
+        }
+        let unknown = self.value & QUALIFIER_MASK & !Qualifier::all().bits();
+        if unknown != 0 {
+            write!(f, "|{:#010x}", unknown)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid page transition component: {0:?}")]
+pub struct ParseTransitionError(pub String);
+
+/// Parses the [`Display`](fmt::Display) form. Names are case-insensitive
+/// and may carry the `PAGE_TRANSITION_` prefix.
+impl FromStr for PageTransition {
+    type Err = ParseTransitionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('|').map(str::trim);
+        let core = parts.next().unwrap_or_default();
+        let mut value = match CoreTransition::from_chromium_name(core) {
+            Some(core) => core.as_u32(),
+            None => core
+                .parse::<u32>()
+                .ok()
+                .filter(|value| *value <= CORE_MASK)
+                .ok_or_else(|| ParseTransitionError(core.to_string()))?,
+        };
+
+        for part in parts {
+            let bits = match Qualifier::from_chromium_name(part) {
+                Some(qualifier) => qualifier.bits(),
+                None => part
+                    .strip_prefix("0x")
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .filter(|bits| bits & CORE_MASK == 0)
+                    .ok_or_else(|| ParseTransitionError(part.to_string()))?,
+            };
+            value |= bits;
+        }
+        Ok(PageTransition::new(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_round_trip() {
+        for value in [
+            0,
+            0x30000000,
+            0x30000001,
+            0x01000008,
+            0xC0000003,
+            0x00400000 | 2,
+        ] {
+            let transition = PageTransition::new(value);
+            let parsed: PageTransition = transition.to_string().parse().unwrap();
+            assert_eq!(parsed.value(), value, "{}", transition);
+        }
+    }
+
+    #[test]
+    fn display_names() {
+        assert_eq!(
+            PageTransition::new(0x30000000).to_string(),
+            "LINK|CHAIN_START|CHAIN_END"
+        );
+        assert_eq!(PageTransition::new(0x0040002a).to_string(), "42|0x00400000");
+    }
+
+    #[test]
+    fn parse() {
+        let transition: PageTransition =
+            "typed | page_transition_from_address_bar".parse().unwrap();
+        assert_eq!(transition.value(), 0x02000001);
+        assert!("LINK|NOT_A_QUALIFIER".parse::<PageTransition>().is_err());
+        assert!("".parse::<PageTransition>().is_err());
+    }
+
+    #[test]
+    fn unknown_core_is_preserved() {
+        let transition = PageTransition::new(0x10000042);
+        assert_eq!(transition.core(), CoreTransition::Unknown(0x42));
+        assert_eq!(transition.core().as_u32(), 0x42);
+    }
+
+    #[test]
+    fn predicates() {
+        let redirect = PageTransition::new(0x40000000);
+        assert!(redirect.is_redirect());
+        assert!(redirect.is_client_redirect());
+        assert!(!redirect.is_server_redirect());
+
+        let back = PageTransition::new(0x01000000);
+        assert!(!back.is_new_navigation());
+        assert!(!PageTransition::new(8).is_new_navigation());
+        assert!(PageTransition::new(1).is_new_navigation());
+
+        assert!(!PageTransition::new(3).is_main_frame());
+        assert!(PageTransition::new(1).is_main_frame());
+        assert!(!PageTransition::new(1).is_web_triggerable());
+    }
+
+    #[test]
+    fn redirect_mask_is_not_a_flag() {
+        let both = Qualifier::ClientRedirect | Qualifier::ServerRedirect;
+        assert_eq!(both, Qualifier::IS_REDIRECT_MASK);
+        assert_eq!(both.iter().count(), 2);
+        assert!(!format!("{:?}", both).contains("IsRedirectMask"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Writes session and tab-restore files, the counterpart of [`SnssFile`].
+//!
+//! [`SnssFile`]: crate::SnssFile
+
+use std::io::{self, Write};
+
+use crate::{
+    IdAndIndex, IdAndTime, PickleWriter, SessionCommand, SessionRestoreIdType, SnssError,
+    SnssFileType, SnssVersion, TabGroupId, TabRestoreIdType, ToPickle,
+};
+
+/// Writes the `SNSS` header and then one command at a time.
+///
+/// Payloads are laid out the way Chromium writes them, including the
+/// padding of its in-memory structs, so the files can be opened by the
+/// browser as well as by [`SnssFile`](crate::SnssFile).
+#[derive(Debug)]
+pub struct SnssWriter<W: Write> {
+    out: W,
+    file_type: SnssFileType,
+    version: SnssVersion,
+}
+
+impl<W: Write> SnssWriter<W> {
+    /// Writes the header of a file of the given type and version.
+    pub fn new(file_type: SnssFileType, version: SnssVersion, mut out: W) -> io::Result<Self> {
+        out.write_all(b"SNSS")?;
+        out.write_all(&version.as_u32().to_le_bytes())?;
+        Ok(Self {
+            out,
+            file_type,
+            version,
+        })
+    }
+
+    pub fn file_type(&self) -> SnssFileType {
+        self.file_type
+    }
+
+    pub fn version(&self) -> SnssVersion {
+        self.version
+    }
+
+    /// Writes a command with an already encoded payload.
+    pub fn write_raw(&mut self, id: u8, payload: &[u8]) -> Result<(), SnssError> {
+        let length = u16::try_from(payload.len() + 1).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("payload of command {} is {} bytes long", id, payload.len()),
+            )
+        })?;
+        self.out.write_all(&length.to_le_bytes())?;
+        self.out.write_all(&[id])?;
+        self.out.write_all(payload)?;
+        Ok(())
+    }
+
+    /// Encodes and writes a command. Fails with
+    /// [`SnssError::InvalidCommandType`] for commands that do not belong
+    /// in this type of file, or that were not decoded when read.
+    pub fn write_command(&mut self, command: &SessionCommand) -> Result<(), SnssError> {
+        let (id, payload) = match self.file_type {
+            SnssFileType::Session => encode_session_command(command),
+            SnssFileType::Tab => encode_tab_command(command),
+        }
+        .or_else(|| {
+            matches!(command, SessionCommand::InitialStateMarker)
+                .then_some((255, Vec::new()))
+                .filter(|_| self.version.has_marker())
+        })
+        .ok_or(SnssError::InvalidCommandType)?;
+        self.write_raw(id, &payload)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+fn encode_session_command(command: &SessionCommand) -> Option<(u8, Vec<u8>)> {
+    use SessionRestoreIdType::*;
+
+    let (id, payload) = match command {
+        SessionCommand::NavigationEntry(entry) => {
+            (CommandUpdateTabNavigation, pickled(entry.as_ref()))
+        }
+        SessionCommand::TabWindow(tab_window) => (
+            CommandSetTabWindow,
+            i32s(&[tab_window.window_id, tab_window.tab_id]),
+        ),
+        SessionCommand::WindowBounds(bounds) => (
+            CommandSetWindowBounds3,
+            i32s(&[
+                bounds.window_id,
+                bounds.x,
+                bounds.y,
+                bounds.width,
+                bounds.height,
+                bounds.show_state.as_i32(),
+            ]),
+        ),
+        SessionCommand::TabIndexInWindow(payload) => {
+            (CommandSetTabIndexInWindow, id_and_index(payload))
+        }
+        SessionCommand::SelectedNavigationIndex(payload) => {
+            (CommandSetSelectedNavigationIndex, id_and_index(payload))
+        }
+        SessionCommand::SelectedTabInIndex(payload) => {
+            (CommandSetSelectedTabInIndex, id_and_index(payload))
+        }
+        SessionCommand::WindowType(payload) => (CommandSetWindowType, id_and_index(payload)),
+        SessionCommand::TabNavigationPathPrunedFromBack(payload) => (
+            CommandTabNavigationPathPrunedFromBack,
+            id_and_index(payload),
+        ),
+        SessionCommand::TabNavigationPathPrunedFromFront(payload) => (
+            CommandTabNavigationPathPrunedFromFront,
+            id_and_index(payload),
+        ),
+        SessionCommand::TabNavigationPathPruned(payload) => (
+            CommandTabNavigationPathPruned,
+            i32s(&[payload.id, payload.index, payload.count]),
+        ),
+        SessionCommand::PinnedState(pinned) => {
+            let mut payload = i32s(&[pinned.tab_id]);
+            payload.push(pinned.pinned as u8);
+            pad(&mut payload, 4);
+            (CommandSetPinnedState, payload)
+        }
+        SessionCommand::TabClosed(payload) => (CommandTabClosed, id_and_time(payload)),
+        SessionCommand::WindowClosed(payload) => (CommandWindowClosed, id_and_time(payload)),
+        SessionCommand::LastActiveTime(payload) => (CommandLastActiveTime, id_and_time(payload)),
+        SessionCommand::ActiveWindow(id) => (CommandSetActiveWindow, i32s(&[*id])),
+        SessionCommand::TabGroup(membership) => {
+            // The id is padded so the token is aligned to eight bytes.
+            let mut payload = i32s(&[membership.tab_id, 0]);
+            let group = membership.group.unwrap_or(TabGroupId { high: 0, low: 0 });
+            payload.extend_from_slice(&group.high.to_le_bytes());
+            payload.extend_from_slice(&group.low.to_le_bytes());
+            payload.push(membership.group.is_some() as u8);
+            pad(&mut payload, 8);
+            (CommandSetTabGroup, payload)
+        }
+        SessionCommand::TabGroupMetadata(metadata) => {
+            (CommandSetTabGroupMetadata2, pickled(metadata))
+        }
+        SessionCommand::TabGuid(guid) => (CommandSetTabGuid, pickled(guid)),
+        _ => return None,
+    };
+    Some((id.as_u8(), payload))
+}
+
+fn encode_tab_command(command: &SessionCommand) -> Option<(u8, Vec<u8>)> {
+    use TabRestoreIdType::*;
+
+    let (id, payload) = match command {
+        SessionCommand::NavigationEntry(entry) => {
+            (CommandUpdateTabNavigation, pickled(entry.as_ref()))
+        }
+        SessionCommand::SelectedNavigationInTab(selected) => {
+            let mut payload = i32s(&[selected.id, selected.index]);
+            payload.extend_from_slice(&selected.timestamp.internal_value().to_le_bytes());
+            (CommandSelectedNavigationInTab, payload)
+        }
+        SessionCommand::EntryPinned(pinned) => (CommandPinnedState, vec![*pinned as u8]),
+        SessionCommand::RestoredEntry(id) => (CommandRestoredEntry, i32s(&[*id])),
+        SessionCommand::ClosedWindow(window) => (CommandWindow, pickled(window)),
+        _ => return None,
+    };
+    Some((id.as_u8(), payload))
+}
+
+fn pickled(value: &impl ToPickle) -> Vec<u8> {
+    let mut pickle = PickleWriter::new();
+    value.to_pickle(&mut pickle);
+    pickle.into_bytes()
+}
+
+fn id_and_index(payload: &IdAndIndex) -> Vec<u8> {
+    i32s(&[payload.id, payload.index])
+}
+
+/// The id is padded so the time is aligned to eight bytes.
+fn id_and_time(payload: &IdAndTime) -> Vec<u8> {
+    let mut data = i32s(&[payload.id, 0]);
+    data.extend_from_slice(&payload.time.internal_value().to_le_bytes());
+    data
+}
+
+fn i32s(values: &[i32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Pads to the struct alignment, as the payload is a copy of the struct.
+fn pad(data: &mut Vec<u8>, alignment: usize) {
+    data.resize(data.len().next_multiple_of(alignment), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::ChromeTime;
+    use crate::{
+        ClosedWindow, PinnedState, SelectedNavigationInTab, SnssFile, TabGroupColor,
+        TabGroupMembership, TabGroupMetadata, TabWindow, WindowBounds, WindowShowState,
+    };
+
+    fn round_trip(file_type: SnssFileType, commands: &[SessionCommand]) -> Vec<SessionCommand> {
+        let mut writer = SnssWriter::new(file_type, SnssVersion::V3, Vec::new()).unwrap();
+        for command in commands {
+            writer.write_command(command).unwrap();
+        }
+        let data = writer.into_inner();
+        let mut snss_file = SnssFile::new(file_type, data.as_slice()).unwrap();
+        snss_file
+            .iter_session_commands()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn assert_round_trip(file_type: SnssFileType, commands: &[SessionCommand]) {
+        let read = round_trip(file_type, commands);
+        assert_eq!(format!("{:?}", read), format!("{:?}", commands));
+    }
+
+    #[test]
+    fn session_commands_round_trip() {
+        let time = ChromeTime::from_internal(13_345_678_901_234_567);
+        let group = TabGroupId { high: 1, low: 2 };
+        assert_round_trip(
+            SnssFileType::Session,
+            &[
+                SessionCommand::WindowType(IdAndIndex { id: 1, index: 0 }),
+                SessionCommand::WindowBounds(WindowBounds {
+                    window_id: 1,
+                    x: -5,
+                    y: 20,
+                    width: 1280,
+                    height: 800,
+                    show_state: WindowShowState::Maximized,
+                }),
+                SessionCommand::TabWindow(TabWindow {
+                    window_id: 1,
+                    tab_id: 2,
+                }),
+                SessionCommand::TabIndexInWindow(IdAndIndex { id: 2, index: 0 }),
+                SessionCommand::PinnedState(PinnedState {
+                    tab_id: 2,
+                    pinned: true,
+                }),
+                SessionCommand::TabGroupMetadata(TabGroupMetadata {
+                    group,
+                    title: "Work".into(),
+                    color: TabGroupColor::Cyan,
+                }),
+                SessionCommand::TabGroup(TabGroupMembership {
+                    tab_id: 2,
+                    group: Some(group),
+                }),
+                SessionCommand::TabGroup(TabGroupMembership {
+                    tab_id: 2,
+                    group: None,
+                }),
+                SessionCommand::SelectedNavigationIndex(IdAndIndex { id: 2, index: 0 }),
+                SessionCommand::LastActiveTime(IdAndTime { id: 2, time }),
+                SessionCommand::TabClosed(IdAndTime { id: 2, time }),
+                SessionCommand::SelectedTabInIndex(IdAndIndex { id: 1, index: 0 }),
+                SessionCommand::ActiveWindow(1),
+                SessionCommand::InitialStateMarker,
+            ],
+        );
+    }
+
+    #[test]
+    fn tab_commands_round_trip() {
+        let timestamp = ChromeTime::from_internal(13_345_678_901_234_567);
+        assert_round_trip(
+            SnssFileType::Tab,
+            &[
+                SessionCommand::ClosedWindow(ClosedWindow {
+                    id: 20,
+                    selected_tab_index: 0,
+                    num_tabs: 1,
+                    timestamp,
+                }),
+                SessionCommand::SelectedNavigationInTab(SelectedNavigationInTab {
+                    id: 21,
+                    index: 0,
+                    timestamp,
+                }),
+                SessionCommand::EntryPinned(true),
+                SessionCommand::RestoredEntry(10),
+            ],
+        );
+    }
+
+    #[test]
+    fn rejects_commands_of_the_other_file_type() {
+        let mut writer = SnssWriter::new(SnssFileType::Tab, SnssVersion::V1, Vec::new()).unwrap();
+        assert!(matches!(
+            writer.write_command(&SessionCommand::ActiveWindow(1)),
+            Err(SnssError::InvalidCommandType)
+        ));
+        assert!(matches!(
+            writer.write_command(&SessionCommand::InitialStateMarker),
+            Err(SnssError::InvalidCommandType)
+        ));
+    }
+}
@@ -0,0 +1,897 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use snss::diff::{Change, SessionDiff};
+use snss::export::{BookmarksExporter, Column, CsvExporter, EventKind, HtmlReport, Timeline};
+use snss::extended_info::PasswordState;
+use snss::firefox::SessionStore;
+use snss::merge;
+use snss::session::{ClosedEntry, SessionState};
+use snss::tasks::TaskGraph;
+use snss::{
+    ChromeTime, ExtendedValue, IdAndIndex, NavigationEntry, NavigationPathPruned, PinnedState,
+    ReferrerPolicy, SessionCommand, SnssFile, SnssFileType, SnssVersion, SnssWriter, TabGroupColor,
+    TabGroupMembership, TabGuid, TabWindow, TypeMask, WindowBounds, WindowShowState,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn commands(snss_file: &mut SnssFile) -> Vec<SessionCommand> {
+    snss_file
+        .iter_session_commands()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn session_file() {
+    let (mut snss_file, detection) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    assert!(detection.is_none());
+    assert_eq!(snss_file.file_type(), SnssFileType::Session);
+    assert_eq!(snss_file.version(), SnssVersion::V3);
+    assert!(snss_file.created().is_some());
+
+    let commands = commands(&mut snss_file);
+    assert_eq!(commands.len(), 25);
+
+    let navigations = commands
+        .iter()
+        .filter(|command| matches!(command, SessionCommand::NavigationEntry(_)))
+        .count();
+    assert_eq!(navigations, 5);
+
+    let tab_windows: Vec<TabWindow> = commands
+        .iter()
+        .filter_map(|command| match command {
+            SessionCommand::TabWindow(tab_window) => Some(*tab_window),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        tab_windows,
+        [2, 3, 4].map(|tab_id| TabWindow {
+            window_id: 1,
+            tab_id
+        })
+    );
+
+    assert!(commands.iter().any(|command| matches!(
+        command,
+        SessionCommand::WindowBounds(WindowBounds {
+            window_id: 1,
+            width: 1280,
+            height: 800,
+            show_state: WindowShowState::Maximized,
+            ..
+        })
+    )));
+
+    let marker = commands
+        .iter()
+        .position(|command| matches!(command, SessionCommand::InitialStateMarker));
+    assert_eq!(marker, Some(18));
+}
+
+#[test]
+fn tabs_file() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Tabs_13345678901234567")).unwrap();
+    assert_eq!(snss_file.file_type(), SnssFileType::Tab);
+
+    let commands = commands(&mut snss_file);
+    assert_eq!(commands.len(), 8);
+    assert!(matches!(
+        commands.last(),
+        Some(SessionCommand::InitialStateMarker)
+    ));
+}
+
+#[test]
+fn detects_renamed_files() {
+    for (name, file_type) in [
+        ("Session_13345678901234567", SnssFileType::Session),
+        ("Tabs_13345678901234567", SnssFileType::Tab),
+    ] {
+        let file = File::open(fixture(name)).unwrap();
+        let (snss_file, detection) = SnssFile::detect(file).unwrap();
+        assert_eq!(detection.file_type, file_type);
+        assert_eq!(snss_file.file_type(), file_type);
+        assert!(detection.confidence > 0.9, "{}: {:?}", name, detection);
+    }
+}
+
+#[test]
+fn truncated_files_do_not_panic() {
+    let data = std::fs::read(fixture("Session_13345678901234567")).unwrap();
+    for length in 0..data.len() {
+        let Ok(mut snss_file) = SnssFile::new(SnssFileType::Session, &data[..length]) else {
+            continue;
+        };
+        for command in snss_file.iter_session_commands() {
+            let _ = command;
+        }
+    }
+}
+
+#[test]
+fn navigation_fields() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let navigations: Vec<_> = commands(&mut snss_file)
+        .into_iter()
+        .filter_map(|command| match command {
+            SessionCommand::NavigationEntry(entry) => Some(entry),
+            _ => None,
+        })
+        .collect();
+
+    let about = &navigations[1];
+    assert_eq!(about.session_id(), 2);
+    assert_eq!(about.index(), 1);
+    assert_eq!(about.url(), "https://example.com/about");
+    assert_eq!(about.title(), "About, \"us\"");
+    assert_eq!(about.referrer_url(), Some("https://example.com/"));
+    assert_eq!(about.http_status(), Some(200));
+    assert_eq!(about.timestamp().to_string(), "2023-11-28T21:01:46.234567Z");
+    assert_eq!(
+        about
+            .extended_map()
+            .get("password_state")
+            .map(String::as_str),
+        Some("1")
+    );
+    assert!(matches!(
+        about.extended_info().get("password_state"),
+        Some(ExtendedValue::PasswordState(PasswordState::NoPasswordField))
+    ));
+    assert_eq!(about.task_id(), Some(101));
+    assert_eq!(about.parent_task_id(), Some(100));
+    assert_eq!(about.root_task_id(), Some(100));
+    assert!(!about.has_post_data());
+    assert_eq!(about.type_mask(), TypeMask::empty());
+    assert_eq!(about.referrer_policy(), Some(ReferrerPolicy::Default));
+    assert!(about.warnings().is_empty());
+
+    assert_eq!(navigations[2].title(), "Chromium 😀");
+}
+
+#[test]
+fn task_graph() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let graph = TaskGraph::from_file(&mut snss_file).unwrap();
+
+    assert_eq!(graph.len(), 5);
+    let roots: Vec<i64> = graph.roots().map(|node| node.task_id).collect();
+    assert_eq!(roots, [100, 104]);
+    assert_eq!(graph.parent(102).map(|node| node.tab_id), Some(2));
+    let children: Vec<i64> = graph.children(101).map(|node| node.task_id).collect();
+    assert_eq!(children, [102, 103]);
+    assert_eq!(graph.tree(100).count(), 4);
+
+    // Tab 3 was opened from the about page in tab 2.
+    assert_eq!(
+        graph.tab_openers().into_iter().collect::<Vec<_>>(),
+        [(3, 2)]
+    );
+
+    let mut dot = Vec::new();
+    graph.write_dot(&mut dot).unwrap();
+    let dot = String::from_utf8(dot).unwrap();
+    assert!(dot.contains("t101 -> t102;"));
+    assert!(dot.contains(r#"label="About, \"us\"\nhttps://example.com/about""#));
+
+    let mut json = Vec::new();
+    graph.write_json(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json["edges"].as_array().unwrap().len(), 3);
+    assert_eq!(json["nodes"][0]["timestamp"], "2023-11-28T21:01:41.234567Z");
+}
+
+#[test]
+fn csv_export() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let mut out = Vec::new();
+    CsvExporter::new()
+        .columns(vec![Column::WindowId, Column::TabId, Column::Title])
+        .write_file(&mut snss_file, &mut out)
+        .unwrap();
+
+    let csv = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "window_id,tab_id,title");
+    assert_eq!(lines[2], r#"1,2,"About, ""us""""#);
+    assert_eq!(lines.len(), 6);
+
+    // A title spanning lines stays one quoted field of one record.
+    let entry = NavigationEntry::new(7, 0, "https://example.com/").with_title("First\nSecond");
+    let mut out = Vec::new();
+    CsvExporter::new()
+        .columns(vec![Column::TabId, Column::Title, Column::Url])
+        .write_entries([&entry], &HashMap::new(), &mut out)
+        .unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("7,\"First\nSecond\",https://example.com/"));
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+    assert_eq!(records.len(), 1);
+    assert_eq!(&records[0][1], "First\nSecond");
+    assert_eq!(&records[0][2], "https://example.com/");
+}
+
+#[test]
+fn session_state() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+
+    assert_eq!(state.active_window_id(), Some(1));
+    let window = state.window(1).unwrap();
+    assert_eq!(window.selected_tab_index, Some(0));
+    assert_eq!(window.bounds.map(|bounds| bounds.width), Some(1280));
+
+    let tabs: Vec<i32> = state.tabs_in_window(1).iter().map(|tab| tab.id).collect();
+    assert_eq!(tabs, [2, 3, 4]);
+
+    let tab = state.tab(2).unwrap();
+    assert_eq!(tab.navigations.len(), 3);
+    assert_eq!(
+        tab.current_navigation()
+            .map(|navigation| navigation.entry.url()),
+        Some("https://example.com/contact")
+    );
+    assert_eq!(
+        tab.guid.as_deref(),
+        Some("2b4d6a0e-1f3c-4b5a-9d8e-7c6b5a4d3e2f")
+    );
+
+    let pinned = state.tab(3).unwrap();
+    assert!(pinned.pinned);
+    let group = state.group(pinned.group.unwrap()).unwrap();
+    assert_eq!(group.title, "Research");
+    assert_eq!(group.color, TabGroupColor::Blue);
+    assert_eq!(group.id.to_string(), "0123456789ABCDEFFEDCBA9876543210");
+
+    assert!(state.tab(4).unwrap().closed.is_some());
+    assert!(state.closed_entries().is_empty());
+}
+
+#[test]
+fn closed_entries() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Tabs_13345678901234567")).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+
+    // Tab 10 was restored again; only the closed window remains.
+    let [ClosedEntry::Window { window, tabs }] = state.closed_entries() else {
+        panic!("{:?}", state.closed_entries());
+    };
+    assert_eq!(window.id, 20);
+    assert_eq!(
+        window.closed.map(|time| time.to_string()).as_deref(),
+        Some("2023-11-28T21:02:11.234567Z")
+    );
+    assert_eq!(tabs.len(), 1);
+    assert!(tabs[0].pinned);
+    assert_eq!(tabs[0].window_id, Some(20));
+    assert_eq!(tabs[0].navigations[0].entry.title(), "In closed window");
+}
+
+#[test]
+fn closed_tab_without_timestamp() {
+    // The id and index only, as written before the closing time was saved.
+    let mut writer = SnssWriter::new(SnssFileType::Tab, SnssVersion::V1, Vec::new()).unwrap();
+    writer.write_raw(4, &[7, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+    writer
+        .write_command(&SessionCommand::NavigationEntry(Box::new(
+            NavigationEntry::new(7, 0, "https://old.example/"),
+        )))
+        .unwrap();
+    let data = writer.into_inner();
+
+    let mut snss_file = SnssFile::new(SnssFileType::Tab, data.as_slice()).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+    let [ClosedEntry::Tab(tab)] = state.closed_entries() else {
+        panic!("{:?}", state.closed_entries());
+    };
+    assert_eq!(tab.id, 7);
+    assert!(tab.closed.is_some_and(|time| time.is_null()));
+    assert_eq!(tab.navigations[0].entry.url(), "https://old.example/");
+}
+
+#[test]
+fn pruned_navigations_are_removed() {
+    let mut writer = SnssWriter::new(SnssFileType::Session, SnssVersion::V3, Vec::new()).unwrap();
+    for (index, url) in [
+        "https://a.example/",
+        "https://b.example/",
+        "https://c.example/",
+        "https://d.example/",
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        writer
+            .write_command(&SessionCommand::NavigationEntry(Box::new(
+                NavigationEntry::new(3, index as i32, url),
+            )))
+            .unwrap();
+    }
+    for command in [
+        SessionCommand::SelectedNavigationIndex(IdAndIndex { id: 3, index: 3 }),
+        SessionCommand::TabNavigationPathPruned(NavigationPathPruned {
+            id: 3,
+            index: 1,
+            count: 2,
+        }),
+    ] {
+        writer.write_command(&command).unwrap();
+    }
+    let data = writer.into_inner();
+
+    let mut snss_file = SnssFile::new(SnssFileType::Session, data.as_slice()).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+    let tab = state.tab(3).unwrap();
+    let navigations: Vec<_> = tab
+        .navigations
+        .iter()
+        .map(|navigation| (navigation.entry.index(), navigation.entry.url()))
+        .collect();
+    assert_eq!(
+        navigations,
+        [(0, "https://a.example/"), (1, "https://d.example/")]
+    );
+    assert_eq!(tab.selected_navigation_index, Some(1));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_export() {
+    use snss::export::SqliteExporter;
+
+    let mut exporter = SqliteExporter::open(Path::new(":memory:")).unwrap();
+    for name in ["Session_13345678901234567", "Tabs_13345678901234567"] {
+        let (mut snss_file, _) = SnssFile::open(&fixture(name)).unwrap();
+        let state = SessionState::from_file(&mut snss_file).unwrap();
+        // Exporting twice replaces the rows instead of duplicating them.
+        exporter.write_state(name, &state).unwrap();
+        exporter.write_state(name, &state).unwrap();
+    }
+
+    let connection = exporter.connection();
+    let count = |sql: &str| -> i64 { connection.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(*) FROM navigations"), 6);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM tabs WHERE closed_time IS NOT NULL"),
+        2
+    );
+    assert_eq!(count("SELECT COUNT(*) FROM groups"), 1);
+    assert_eq!(
+        count("SELECT COUNT(*) FROM closed_entries WHERE kind = 'window'"),
+        1
+    );
+    assert_eq!(
+        count("SELECT offset FROM navigations WHERE url = 'https://example.com/contact'"),
+        869
+    );
+}
+
+#[test]
+fn html_report() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+    let html = HtmlReport::new().title("<Session>").render(&state);
+
+    assert!(html.contains("<title>&lt;Session&gt;</title>"));
+    assert!(html.contains(r#"<a href="https://example.com/about" title="https://example.com/about">About, &quot;us&quot;</a>"#));
+    assert!(html.contains(r#"<span class="group" style="background: #1a73e8">Research</span>"#));
+    assert!(html.contains(r##"<li class="selected"><a href="#tab-2">Contact</a></li>"##));
+    assert!(html.contains(r#"<li class="tab closed" id="tab-4">"#));
+
+    let (mut snss_file, _) = SnssFile::open(&fixture("Tabs_13345678901234567")).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+    let html = HtmlReport::new().render(&state);
+    assert!(html.contains("<h2>Recently closed</h2>"));
+    assert!(html.contains("In closed window"));
+}
+
+#[test]
+fn bookmarks_export() {
+    let (mut session, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let (mut tabs, _) = SnssFile::open(&fixture("Tabs_13345678901234567")).unwrap();
+    let session = SessionState::from_file(&mut session).unwrap();
+    let tabs = SessionState::from_file(&mut tabs).unwrap();
+
+    let mut out = Vec::new();
+    BookmarksExporter::new()
+        .write(&[&session, &tabs], &mut out)
+        .unwrap();
+    let html = String::from_utf8(out).unwrap();
+
+    assert!(html.starts_with("<!DOCTYPE NETSCAPE-Bookmark-file-1>"));
+    assert!(html.contains("<DT><H3>Recovered tabs</H3>"));
+    assert!(html.contains(
+        "<DT><H3>Window 1</H3>\n<DL><p>\n<DT><A HREF=\"https://example.com/contact\" ADD_DATE=\"1701205361\">Contact</A>\n"
+    ));
+    assert!(
+        html.contains("<DT><H3>Research</H3>\n<DL><p>\n<DT><A HREF=\"https://www.chromium.org/\"")
+    );
+    assert!(
+        html.contains("<DT><H3>Closed tabs</H3>\n<DL><p>\n<DT><A HREF=\"https://example.org/\"")
+    );
+    assert!(html.contains("<DT><H3>Recently closed</H3>"));
+    assert!(html.contains("<DT><A HREF=\"https://window.example/\""));
+}
+
+#[test]
+fn firefox_sessionstore() {
+    let (mut session, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let (mut tabs, _) = SnssFile::open(&fixture("Tabs_13345678901234567")).unwrap();
+    let session = SessionState::from_file(&mut session).unwrap();
+    let tabs = SessionState::from_file(&mut tabs).unwrap();
+    let store = SessionStore::from_states(&[&session, &tabs]);
+
+    assert_eq!(store.selected_window, 1);
+    let window = &store.windows[0];
+    assert_eq!(window.sizemode.as_deref(), Some("maximized"));
+    assert_eq!(window.tabs.len(), 2);
+    assert_eq!(window.selected, 1);
+
+    let first = &window.tabs[0];
+    assert_eq!(first.entries.len(), 3);
+    assert_eq!(first.index, 3);
+    assert_eq!(first.entries[2].url, "https://example.com/contact");
+    assert_eq!(
+        first.entries[1].referrer.as_deref(),
+        Some("https://example.com/")
+    );
+
+    let pinned = &window.tabs[1];
+    assert!(pinned.pinned);
+    assert_eq!(pinned.group_id, Some(window.groups[0].id.clone()));
+    assert_eq!(window.groups[0].name, "Research");
+    assert_eq!(window.groups[0].color, "blue");
+
+    assert_eq!(window.closed_tabs.len(), 1);
+    assert_eq!(window.closed_tabs[0].title, "Example Org");
+    assert_eq!(window.closed_tabs[0].pos, 2);
+
+    assert_eq!(store.closed_windows.len(), 1);
+    assert_eq!(
+        store.closed_windows[0].tabs[0].entries[0].url,
+        "https://window.example/"
+    );
+
+    let mut framed = Vec::new();
+    store.write_mozlz4(&mut framed).unwrap();
+    assert!(framed.starts_with(b"mozLz40\0"));
+    assert_eq!(SessionStore::from_mozlz4(&framed).unwrap(), store);
+}
+
+#[test]
+fn firefox_round_trip() {
+    let (mut session, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let original = SessionState::from_file(&mut session).unwrap();
+    let store = SessionStore::from_states(&[&original]);
+
+    let mut data = Vec::new();
+    store.write_session(&mut data).unwrap();
+    let mut imported = SnssFile::new(SnssFileType::Session, data.as_slice()).unwrap();
+    let imported = SessionState::from_file(&mut imported).unwrap();
+
+    let urls = |state: &SessionState, window_id| -> Vec<String> {
+        state
+            .tabs_in_window(window_id)
+            .into_iter()
+            .filter(|tab| tab.closed.is_none())
+            .map(|tab| tab.current_navigation().unwrap().entry.url().to_string())
+            .collect()
+    };
+    assert_eq!(urls(&imported, 1), urls(&original, 1));
+    assert_eq!(imported.active_window_id(), Some(1));
+    assert_eq!(
+        imported.window(1).unwrap().bounds,
+        original.window(1).unwrap().bounds
+    );
+
+    let pinned = imported.tab(3).unwrap();
+    assert!(pinned.pinned);
+    let group = imported.group(pinned.group.unwrap()).unwrap();
+    assert_eq!(group.title, "Research");
+    assert_eq!(group.color, TabGroupColor::Blue);
+    assert_eq!(pinned.group, original.tab(3).unwrap().group);
+}
+
+#[test]
+fn firefox_import_without_index() {
+    let json = br#"{
+        "version": ["sessionrestore", 1],
+        "windows": [{
+            "tabs": [{
+                "entries": [
+                    {"url": "https://example.net/"},
+                    {"url": "https://example.net/next"}
+                ]
+            }]
+        }]
+    }"#;
+    let store = SessionStore::from_json(json).unwrap();
+    let mut data = Vec::new();
+    store.write_session(&mut data).unwrap();
+    let mut snss_file = SnssFile::new(SnssFileType::Session, data.as_slice()).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+
+    let tab = state.tabs().next().unwrap();
+    assert_eq!(tab.selected_navigation_index, Some(1));
+    assert_eq!(
+        tab.current_navigation().unwrap().entry.url(),
+        "https://example.net/next"
+    );
+}
+
+#[test]
+fn firefox_import() {
+    let json = br#"{
+        "version": ["sessionrestore", 1],
+        "windows": [{
+            "tabs": [
+                {"entries": [], "lastAccessed": 0},
+                {
+                    "entries": [
+                        {"url": "about:newtab", "title": "New Tab"},
+                        {"url": "https://example.net/", "title": "Net",
+                         "referrer": "https://example.com/", "referrerPolicy": 8},
+                        {"url": "https://example.net/next"}
+                    ],
+                    "index": 2,
+                    "lastAccessed": 1701205307234,
+                    "image": null,
+                    "groupId": "1712345678-1"
+                }
+            ],
+            "selected": 2,
+            "groups": [{"id": "1712345678-1", "name": "Shopping", "color": "green"}],
+            "_closedTabs": []
+        }],
+        "selectedWindow": 1,
+        "_closedWindows": []
+    }"#;
+    let store = SessionStore::from_json(json).unwrap();
+    let mut data = Vec::new();
+    store.write_session(&mut data).unwrap();
+    let mut snss_file = SnssFile::new(SnssFileType::Session, data.as_slice()).unwrap();
+    let state = SessionState::from_file(&mut snss_file).unwrap();
+
+    let tabs: Vec<_> = state.tabs().collect();
+    assert_eq!(tabs.len(), 1);
+    let tab = tabs[0];
+    assert_eq!(tab.window_id, Some(1));
+    assert_eq!(tab.navigations[0].entry.url(), "chrome://newtab/");
+    let current = &tab.current_navigation().unwrap().entry;
+    assert_eq!(current.url(), "https://example.net/");
+    assert_eq!(current.referrer_url(), Some("https://example.com/"));
+    assert_eq!(
+        current.referrer_policy(),
+        Some(ReferrerPolicy::StrictOriginWhenCrossOrigin)
+    );
+    assert_eq!(
+        tab.last_active,
+        Some(ChromeTime::from_unix_micros(1_701_205_307_234_000))
+    );
+    assert_eq!(state.window(1).unwrap().selected_tab_index, Some(0));
+    let group = state.group(tab.group.unwrap()).unwrap();
+    assert_eq!(group.title, "Shopping");
+    assert_eq!(group.color, TabGroupColor::Green);
+}
+
+#[test]
+fn timeline() {
+    let mut timeline = Timeline::new();
+    for name in ["Session_13345678901234567", "Tabs_13345678901234567"] {
+        let (mut snss_file, _) = SnssFile::open(&fixture(name)).unwrap();
+        timeline.add_file(name, &mut snss_file).unwrap();
+    }
+
+    let events = timeline.events();
+    assert_eq!(events.len(), 14);
+    assert!(events.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    assert_eq!(events[0].kind, EventKind::FileCreated);
+    assert_eq!(events[0].offset, None);
+
+    let closed = events
+        .iter()
+        .find(|event| event.kind == EventKind::TabClosed && event.tab_id == Some(4))
+        .unwrap();
+    assert_eq!(closed.source, "Session_13345678901234567");
+    assert_eq!(closed.offset, Some(1244));
+    assert_eq!(closed.window_id, Some(1));
+    assert_eq!(closed.url.as_deref(), Some("https://example.org/"));
+
+    let mut bodyfile = Vec::new();
+    timeline.write_bodyfile(&mut bodyfile).unwrap();
+    let bodyfile = String::from_utf8(bodyfile).unwrap();
+    assert_eq!(bodyfile.lines().count(), 14);
+    assert!(bodyfile.contains(
+        "0|[Chrome Session] Session_13345678901234567@1244: Tab 4 closed: \
+         https://example.org/ (Example Org)|0|0|0|0|0|0|1701205363|0|0\n"
+    ));
+
+    let mut jsonl = Vec::new();
+    timeline.write_jsonl(&mut jsonl).unwrap();
+    let events: Vec<serde_json::Value> = String::from_utf8(jsonl)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let closed = events.iter().find(|event| event["offset"] == 1244).unwrap();
+    assert_eq!(closed["data_type"], "chrome:session:tab_closed");
+    assert_eq!(closed["timestamp_desc"], "Closed Time");
+    assert_eq!(closed["filename"], "Session_13345678901234567");
+    assert_eq!(closed["timestamp"], 1_701_205_363_234_567_i64);
+    assert_eq!(closed["date_time"]["timestamp"], 13_345_678_963_234_567_i64);
+}
+
+#[test]
+fn session_diff() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let old = SessionState::from_file(&mut snss_file).unwrap();
+
+    let mut new = old.clone();
+    let time = ChromeTime::from_unix_micros(1_701_300_000_000_000);
+    for command in [
+        SessionCommand::TabIndexInWindow(IdAndIndex { id: 3, index: 0 }),
+        SessionCommand::TabIndexInWindow(IdAndIndex { id: 2, index: 1 }),
+        SessionCommand::PinnedState(PinnedState {
+            tab_id: 3,
+            pinned: false,
+        }),
+        SessionCommand::TabGroup(TabGroupMembership {
+            tab_id: 3,
+            group: None,
+        }),
+        SessionCommand::NavigationEntry(Box::new(
+            NavigationEntry::new(2, 3, "https://example.com/new").with_timestamp(time),
+        )),
+        SessionCommand::TabWindow(TabWindow {
+            window_id: 9,
+            tab_id: 5,
+        }),
+        SessionCommand::NavigationEntry(Box::new(NavigationEntry::new(
+            5,
+            0,
+            "https://opened.example/",
+        ))),
+    ] {
+        new.apply(u64::MAX, command);
+    }
+
+    let diff = SessionDiff::between(&old, &new);
+    let mut text = Vec::new();
+    diff.write_text(&mut text).unwrap();
+    assert_eq!(
+        String::from_utf8(text).unwrap(),
+        "+ window 9\n\
+         ~ tab 3 moved from window 1 #1 to window 1 #0\n\
+         ~ tab 3 regrouped from group \"Research\" to no group\n\
+         ~ tab 3 unpinned\n\
+         + tab 2 navigation 3: https://example.com/new\n\
+         + tab 5 in window 9 #?: https://opened.example/\n"
+    );
+
+    let reverse = SessionDiff::between(&new, &old);
+    assert!(
+        reverse
+            .changes()
+            .contains(&Change::WindowClosed { window_id: 9 })
+    );
+    assert!(reverse.changes().contains(&Change::TabPinned {
+        tab_id: 3,
+        guid: None
+    }));
+
+    let mut json = Vec::new();
+    diff.write_json(&mut json).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(json[0]["change"], "window_opened");
+    assert_eq!(json[2]["from_group"]["title"], "Research");
+    assert_eq!(json[2]["to_group"], serde_json::Value::Null);
+}
+
+#[test]
+fn session_diff_matches_tabs_by_guid() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let old = SessionState::from_file(&mut snss_file).unwrap();
+    let tab = old.tab(2).unwrap();
+
+    // The same tab after a restart, with new session ids.
+    let mut new = SessionState::new(SnssFileType::Session);
+    new.apply(
+        0,
+        SessionCommand::TabWindow(TabWindow {
+            window_id: 7,
+            tab_id: 12,
+        }),
+    );
+    new.apply(
+        0,
+        SessionCommand::TabGuid(TabGuid {
+            tab_id: 12,
+            guid: tab.guid.clone().unwrap(),
+        }),
+    );
+    for navigation in &tab.navigations {
+        let entry = &navigation.entry;
+        new.apply(
+            0,
+            SessionCommand::NavigationEntry(Box::new(
+                NavigationEntry::new(12, entry.index(), entry.url())
+                    .with_timestamp(entry.timestamp()),
+            )),
+        );
+    }
+
+    let diff = SessionDiff::between(&old, &new);
+    assert_eq!(
+        diff.changes(),
+        [Change::TabClosed {
+            tab_id: 3,
+            guid: None,
+            window_id: Some(1),
+            url: Some("https://www.chromium.org/".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn merge_sessions() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let old = SessionState::from_file(&mut snss_file).unwrap();
+
+    // A later snapshot of the same run, with a new page and a new window.
+    let mut newer = old.clone();
+    let time = ChromeTime::from_unix_micros(1_701_300_000_000_000);
+    for command in [
+        SessionCommand::NavigationEntry(Box::new(
+            NavigationEntry::new(2, 3, "https://example.com/new").with_timestamp(time),
+        )),
+        SessionCommand::SelectedNavigationIndex(IdAndIndex { id: 2, index: 3 }),
+        SessionCommand::TabWindow(TabWindow {
+            window_id: 9,
+            tab_id: 5,
+        }),
+        SessionCommand::NavigationEntry(Box::new(NavigationEntry::new(
+            5,
+            0,
+            "https://opened.example/",
+        ))),
+    ] {
+        newer.apply(u64::MAX, command);
+    }
+
+    // A file of a later run: tab 2 again under a new id, and an unrelated
+    // tab that reuses id 3.
+    let mut restarted = SessionState::new(SnssFileType::Session);
+    let tab = old.tab(2).unwrap();
+    for command in [
+        SessionCommand::TabWindow(TabWindow {
+            window_id: 7,
+            tab_id: 12,
+        }),
+        SessionCommand::TabGuid(TabGuid {
+            tab_id: 12,
+            guid: tab.guid.clone().unwrap(),
+        }),
+        SessionCommand::NavigationEntry(Box::new(
+            NavigationEntry::new(12, 0, tab.navigations[0].entry.url())
+                .with_timestamp(tab.navigations[0].entry.timestamp()),
+        )),
+        SessionCommand::TabWindow(TabWindow {
+            window_id: 7,
+            tab_id: 3,
+        }),
+        SessionCommand::NavigationEntry(Box::new(NavigationEntry::new(
+            3,
+            0,
+            "https://unrelated.example/",
+        ))),
+    ] {
+        restarted.apply(0, command);
+    }
+
+    let merged = merge::merge(&[&old, &newer, &restarted]);
+    assert_eq!(merged.windows().count(), 2);
+    assert_eq!(merged.active_window_id(), Some(1));
+
+    let urls = |window_id| -> Vec<&str> {
+        merged
+            .tabs_in_window(window_id)
+            .iter()
+            .map(|tab| tab.current_navigation().unwrap().entry.url())
+            .collect()
+    };
+    assert_eq!(
+        urls(1),
+        [
+            "https://example.com/new",
+            "https://www.chromium.org/",
+            "https://unrelated.example/",
+        ]
+    );
+    assert_eq!(urls(5), ["https://opened.example/"]);
+    let kept = &merged.tabs_in_window(1)[0];
+    assert_eq!(kept.navigations.len(), 4);
+    assert_eq!(kept.guid, tab.guid);
+    let pinned = &merged.tabs_in_window(1)[1];
+    assert!(pinned.pinned);
+    assert_eq!(
+        merged.group(pinned.group.unwrap()).unwrap().title,
+        "Research"
+    );
+
+    let mut data = Vec::new();
+    merged.write_session(&mut data).unwrap();
+    let mut snss_file = SnssFile::new(SnssFileType::Session, data.as_slice()).unwrap();
+    let read = SessionState::from_file(&mut snss_file).unwrap();
+    let layout = |state: &SessionState| -> Vec<(i32, Option<i32>, Option<i32>, String)> {
+        state
+            .tabs()
+            .map(|tab| {
+                let url = tab.current_navigation().unwrap().entry.url().to_string();
+                (tab.id, tab.window_id, tab.index, url)
+            })
+            .collect()
+    };
+    assert_eq!(layout(&read), layout(&merged));
+    assert_eq!(read.active_window_id(), Some(1));
+}
+
+#[test]
+fn session_diff_does_not_pair_reused_ids() {
+    let (mut snss_file, _) = SnssFile::open(&fixture("Session_13345678901234567")).unwrap();
+    let old = SessionState::from_file(&mut snss_file).unwrap();
+
+    // After a restart, an unrelated tab was given the id of tab 2.
+    let mut new = SessionState::new(SnssFileType::Session);
+    for command in [
+        SessionCommand::TabWindow(TabWindow {
+            window_id: 1,
+            tab_id: 2,
+        }),
+        SessionCommand::TabGuid(TabGuid {
+            tab_id: 2,
+            guid: "9f8e7d6c-5b4a-4392-8170-6f5e4d3c2b1a".to_string(),
+        }),
+        SessionCommand::NavigationEntry(Box::new(NavigationEntry::new(
+            2,
+            0,
+            "https://unrelated.example/",
+        ))),
+    ] {
+        new.apply(0, command);
+    }
+
+    let diff = SessionDiff::between(&old, &new);
+    assert_eq!(
+        diff.changes()[0],
+        Change::TabOpened {
+            tab_id: 2,
+            guid: Some("9f8e7d6c-5b4a-4392-8170-6f5e4d3c2b1a".to_string()),
+            window_id: Some(1),
+            index: None,
+            url: Some("https://unrelated.example/".to_string()),
+        }
+    );
+    assert!(diff.changes().contains(&Change::TabClosed {
+        tab_id: 2,
+        guid: old.tab(2).unwrap().guid.clone(),
+        window_id: Some(1),
+        url: Some("https://example.com/contact".to_string()),
+    }));
+    assert!(
+        !diff
+            .changes()
+            .iter()
+            .any(|change| matches!(change, Change::NavigationAdded { .. }))
+    );
+}
@@ -0,0 +1,161 @@
+#!/usr/bin/env python3
+"""Regenerates the SNSS fixtures in this directory.
+
+The files mirror what Chromium writes: a v3 `Session_` file with two
+windows' worth of state followed by the initial-state marker and a few
+appended commands, and a v3 `Tabs_` file with a closed tab, a closed
+window and a restored entry. Timestamps are microseconds since 1601.
+
+It also refreshes the seed corpora under `fuzz/corpus`.
+"""
+
+import os
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+T0 = 13345678901234567
+
+
+def align(data):
+    return data + b"\0" * (-len(data) % 4)
+
+
+def int32(value):
+    return struct.pack("<i", value)
+
+
+def uint32(value):
+    return struct.pack("<I", value)
+
+
+def int64(value):
+    return struct.pack("<q", value)
+
+
+def uint64(value):
+    return struct.pack("<Q", value)
+
+
+def string(value):
+    data = value.encode() if isinstance(value, str) else value
+    return align(uint32(len(data)) + data)
+
+
+def string16(value):
+    data = value.encode("utf-16-le")
+    return align(uint32(len(data) // 2) + data)
+
+
+def pickle(body):
+    return uint32(len(body)) + body
+
+
+def navigation(tab_id, index, url, title, timestamp, transition=0,
+               referrer="", task=(0, 0, 0), extended=None, status=200):
+    body = int32(tab_id) + int32(index) + string(url) + string16(title)
+    body += string(b"") + uint32(transition) + uint32(0) + string(referrer)
+    body += int32(0) + string(url) + int32(0) + int64(timestamp)
+    body += string16("") + int32(status) + int32(1)
+    extended = extended or {}
+    body += int32(len(extended))
+    for key, value in extended.items():
+        body += string(key) + string(value)
+    body += int64(task[0]) + int64(task[1]) + int64(task[2]) + int32(0)
+    return pickle(body)
+
+
+def command(command_id, payload):
+    return struct.pack("<H", len(payload) + 1) + bytes([command_id]) + payload
+
+
+def snss(commands, version=3):
+    return b"SNSS" + uint32(version) + b"".join(commands)
+
+
+def session_file():
+    group_high, group_low = 0x0123456789ABCDEF, 0xFEDCBA9876543210
+    return snss([
+        command(9, int32(1) + int32(0)),                       # window type
+        command(0, int32(1) + int32(2)),                       # tab 2 -> window 1
+        command(2, int32(2) + int32(0)),                       # tab index
+        command(6, navigation(2, 0, "https://example.com/", "Example", T0,
+                              transition=1 | 0x10000000 | 0x20000000,
+                              task=(100, 0, 100))),
+        command(6, navigation(2, 1, "https://example.com/about", "About, \"us\"",
+                              T0 + 5_000_000, referrer="https://example.com/",
+                              task=(101, 100, 100),
+                              extended={"password_state": "1"})),
+        command(7, int32(2) + int32(1)),                       # selected navigation
+        command(28, pickle(int32(2) + string("2b4d6a0e-1f3c-4b5a-9d8e-7c6b5a4d3e2f"))),
+        command(21, int32(2) + b"\0" * 4 + int64(T0 + 6_000_000)),
+        command(0, int32(1) + int32(3)),                       # tab 3 -> window 1
+        command(2, int32(3) + int32(1)),
+        command(6, navigation(3, 0, "https://www.chromium.org/", "Chromium 😀",
+                              T0 + 7_000_000, task=(102, 101, 100))),
+        command(7, int32(3) + int32(0)),
+        command(12, int32(3) + b"\1" + b"\0" * 3),             # pinned
+        command(25, int32(3) + b"\0" * 4 + uint64(group_high) + uint64(group_low)
+                + b"\1" + b"\0" * 7),
+        command(27, pickle(uint64(group_high) + uint64(group_low)
+                           + string16("Research") + uint32(1))),
+        command(8, int32(1) + int32(0)),                       # selected tab
+        command(14, int32(1) + int32(10) + int32(20) + int32(1280) + int32(800)
+                + int32(3)),
+        command(20, int32(1)),                                 # active window
+        command(255, b""),                                     # initial-state marker
+        command(6, navigation(2, 2, "https://example.com/contact", "Contact",
+                              T0 + 60_000_000, task=(103, 101, 100))),
+        command(7, int32(2) + int32(2)),
+        command(0, int32(1) + int32(4)),
+        command(2, int32(4) + int32(2)),
+        command(6, navigation(4, 0, "https://example.org/", "Example Org",
+                              T0 + 61_000_000, task=(104, 0, 104))),
+        command(16, int32(4) + b"\0" * 4 + int64(T0 + 62_000_000)),  # tab closed
+    ])
+
+
+def tabs_file():
+    window = pickle(int32(20) + int32(0) + int32(1) + int64(T0 + 30_000_000))
+    return snss([
+        command(4, int32(10) + int32(0) + int64(T0 + 10_000_000)),
+        command(1, navigation(10, 0, "https://closed.example/", "Closed tab",
+                              T0 + 9_000_000)),
+        command(9, window),
+        command(4, int32(21) + int32(0) + int64(T0 + 30_000_000)),
+        command(5, b"\1"),
+        command(1, navigation(21, 0, "https://window.example/", "In closed window",
+                              T0 + 29_000_000)),
+        command(2, int32(10)),                                 # tab 10 restored
+        command(255, b""),
+    ])
+
+
+def write(path, data):
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "wb") as f:
+        f.write(data)
+
+
+def write_fuzz_seeds():
+    corpus = os.path.join(HERE, "..", "..", "fuzz", "corpus")
+    write(os.path.join(corpus, "snss_file", "session"), session_file())
+    write(os.path.join(corpus, "snss_file", "tabs"), tabs_file())
+
+    entry = navigation(2, 1, "https://example.com/about", "About", T0,
+                       referrer="https://example.com/", task=(101, 100, 100),
+                       extended={"password_state": "1"})
+    write(os.path.join(corpus, "pickle_iterator", "navigation"), entry)
+    # The navigation target supplies its own pickle header.
+    write(os.path.join(corpus, "navigation_entry", "navigation"), entry[4:])
+    legacy = navigation(2, 0, "https://example.com/", "", T0)
+    write(os.path.join(corpus, "navigation_entry", "minimal"), legacy[4:4 + 32])
+
+
+def main():
+    write(os.path.join(HERE, "Session_13345678901234567"), session_file())
+    write(os.path.join(HERE, "Tabs_13345678901234567"), tabs_file())
+    write_fuzz_seeds()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,120 @@
+use snss::{
+    FromPickle, NavigationEntry, PickleIterator, PickleWriter, ReferrerPolicy, ToPickle, TypeMask,
+};
+
+/// The fields every version of Chromium has written.
+fn required_fields() -> PickleWriter {
+    let mut writer = PickleWriter::new();
+    writer.write_int32(2);
+    writer.write_int32(0);
+    writer.write_string("https://example.com/");
+    writer.write_string16("Example");
+    writer.write_bytes(b"");
+    writer.write_uint32(1);
+    writer
+}
+
+fn parse(writer: PickleWriter) -> NavigationEntry {
+    let mut pickle = PickleIterator::new(writer.into_bytes(), 4).unwrap();
+    <NavigationEntry as FromPickle>::from_pickle(&mut pickle).unwrap()
+}
+
+#[test]
+fn absent_trailing_fields_are_not_warnings() {
+    let mut writer = required_fields();
+    writer.write_uint32(0);
+    writer.write_string("https://referrer.example/");
+    let entry = parse(writer);
+    assert!(entry.warnings().is_empty());
+
+    // The defaults written back for the absent fields read back cleanly.
+    let mut rewritten = PickleWriter::new();
+    entry.to_pickle(&mut rewritten);
+    assert!(parse(rewritten).warnings().is_empty());
+}
+
+#[test]
+fn malformed_trailing_field_is_a_warning() {
+    let mut writer = required_fields();
+    writer.write_uint32(0);
+    // A referrer whose length runs past the end of the pickle.
+    writer.write_uint32(1000);
+    writer.write_string("short");
+    let entry = parse(writer);
+
+    let warnings = entry.warnings();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].field, "referrer_url");
+    assert_eq!(warnings[0].offset, 68);
+}
+
+#[test]
+fn truncated_required_field_is_an_error() {
+    let mut writer = PickleWriter::new();
+    writer.write_int32(2);
+    writer.write_int32(0);
+    let mut pickle = PickleIterator::new(writer.into_bytes(), 4).unwrap();
+    assert!(NavigationEntry::from_pickle(&mut pickle).is_err());
+}
+
+#[test]
+fn unknown_type_mask_bits_and_referrer_policy_are_kept() {
+    let mut writer = required_fields();
+    writer.write_uint32(0x05);
+    writer.write_string("");
+    writer.write_int32(0);
+    writer.write_string("");
+    writer.write_bool(false);
+    writer.write_int64(0);
+    writer.write_string16("");
+    writer.write_int32(200);
+    writer.write_int32(42);
+    let entry = parse(writer);
+
+    assert!(entry.has_post_data());
+    assert_eq!(entry.type_mask().bits(), 0x05);
+    assert!(entry.type_mask().contains(TypeMask::HasPostData));
+    assert_eq!(entry.referrer_policy(), Some(ReferrerPolicy::Unknown(42)));
+
+    let mut rewritten = PickleWriter::new();
+    entry.to_pickle(&mut rewritten);
+    let reread = parse(rewritten);
+    assert_eq!(reread.type_mask().bits(), 0x05);
+    assert_eq!(reread.referrer_policy(), Some(ReferrerPolicy::Unknown(42)));
+}
+
+#[test]
+fn missing_referrer_policy_is_written_as_default() {
+    // Entries from before the policy was saved end at the HTTP status.
+    let mut writer = required_fields();
+    writer.write_uint32(0);
+    writer.write_string("");
+    writer.write_int32(0);
+    writer.write_string("");
+    writer.write_bool(false);
+    writer.write_int64(0);
+    writer.write_string16("");
+    writer.write_int32(200);
+    let entry = parse(writer);
+    assert_eq!(entry.referrer_policy(), None);
+
+    let mut rewritten = PickleWriter::new();
+    entry.to_pickle(&mut rewritten);
+    assert_eq!(
+        parse(rewritten).referrer_policy(),
+        Some(ReferrerPolicy::Default)
+    );
+}
+
+#[test]
+fn missing_task_ids_are_written_as_unset() {
+    let entry = parse(required_fields());
+    assert_eq!(entry.task_id(), None);
+
+    let mut rewritten = PickleWriter::new();
+    entry.to_pickle(&mut rewritten);
+    let entry = parse(rewritten);
+    assert_eq!(entry.task_id(), Some(-1));
+    assert_eq!(entry.parent_task_id(), Some(-1));
+    assert_eq!(entry.root_task_id(), Some(-1));
+}
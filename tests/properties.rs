@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use proptest::prelude::*;
+
+use snss::{
+    FromPickle, NavigationEntry, PickleIterator, PickleWriter, SnssFile, SnssFileType, ToPickle,
+};
+
+#[derive(Debug, Clone, PartialEq, FromPickle, ToPickle)]
+struct Payload {
+    id: i32,
+    name: String,
+    #[pickle(string16)]
+    title: String,
+    values: Vec<u64>,
+    extended: HashMap<String, String>,
+    timestamp: Option<i64>,
+    #[pickle(string16)]
+    label: Option<String>,
+}
+
+#[derive(Debug, PartialEq, FromPickle, ToPickle)]
+struct Pair(i32, Option<bool>);
+
+fn payload() -> impl Strategy<Value = Payload> {
+    (
+        any::<i32>(),
+        ".*",
+        ".*",
+        proptest::collection::vec(any::<u64>(), 0..8),
+        proptest::collection::hash_map(".*", ".*", 0..4),
+        proptest::option::of(any::<i64>()),
+        proptest::option::of(".*"),
+    )
+        .prop_map(
+            |(id, name, title, values, extended, timestamp, label)| Payload {
+                id,
+                name,
+                title,
+                values,
+                extended,
+                // A label can only follow a timestamp.
+                label: label.filter(|_| timestamp.is_some()),
+                timestamp,
+            },
+        )
+}
+
+fn write<T: ToPickle>(value: &T) -> Vec<u8> {
+    let mut writer = PickleWriter::new();
+    value.to_pickle(&mut writer);
+    writer.into_bytes()
+}
+
+/// Wraps arbitrary bytes in a pickle header so they reach the field readers
+/// instead of failing the length check.
+fn pickle(body: &[u8]) -> PickleIterator {
+    let mut data = (body.len() as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(body);
+    PickleIterator::new(data, 4).unwrap()
+}
+
+fn command(id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut data = ((payload.len() + 1) as u16).to_le_bytes().to_vec();
+    data.push(id);
+    data.extend_from_slice(payload);
+    data
+}
+
+proptest! {
+    #[test]
+    fn pickle_reads_never_panic(body in proptest::collection::vec(any::<u8>(), 0..256)) {
+        let mut iterator = pickle(&body);
+        while iterator.remaining() > 0 {
+            let before = iterator.remaining();
+            let _ = iterator.read_string();
+            let _ = iterator.read_string16_lossy();
+            let _ = iterator.read_bool();
+            let _ = iterator.read_int64();
+            if iterator.remaining() == before {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn pickle_length_prefixes_are_bounded(length in any::<u32>(), tail in proptest::collection::vec(any::<u8>(), 0..64)) {
+        let mut body = length.to_le_bytes().to_vec();
+        body.extend_from_slice(&tail);
+
+        let mut iterator = pickle(&body);
+        if let Ok(string) = iterator.read_string16_lossy() {
+            prop_assert!(string.units().len() * 2 <= tail.len());
+        }
+        let mut iterator = pickle(&body);
+        if let Ok(string) = iterator.read_string() {
+            prop_assert!(string.len() <= tail.len());
+        }
+    }
+
+    #[test]
+    fn navigation_entries_never_panic(body in proptest::collection::vec(any::<u8>(), 0..512)) {
+        let _ = NavigationEntry::from_pickle(&mut pickle(&body));
+    }
+
+    #[test]
+    fn command_streams_never_panic(
+        version in prop_oneof![Just(1u32), Just(3u32)],
+        commands in proptest::collection::vec(
+            (any::<u8>(), proptest::collection::vec(any::<u8>(), 0..64)),
+            0..16,
+        ),
+        file_type in prop_oneof![Just(SnssFileType::Session), Just(SnssFileType::Tab)],
+    ) {
+        let mut data = b"SNSS".to_vec();
+        data.extend(version.to_le_bytes());
+        for (id, payload) in &commands {
+            data.extend(command(*id, payload));
+        }
+
+        let mut snss_file = SnssFile::new(file_type, data.as_slice()).unwrap();
+        prop_assert!(snss_file.iter_session_commands().count() <= commands.len());
+
+        let _ = SnssFile::detect(data.as_slice()).unwrap();
+    }
+
+    #[test]
+    fn derived_payload_round_trip(payload in payload()) {
+        let bytes = write(&payload);
+        let mut pickle = PickleIterator::new(bytes, 4).unwrap();
+        prop_assert_eq!(Payload::from_pickle(&mut pickle).unwrap(), payload);
+        prop_assert_eq!(pickle.remaining(), 0);
+    }
+
+    #[test]
+    fn derived_tuple_round_trip(id in any::<i32>(), flag in proptest::option::of(any::<bool>())) {
+        let pair = Pair(id, flag);
+        let mut pickle = PickleIterator::new(write(&pair), 4).unwrap();
+        prop_assert_eq!(Pair::from_pickle(&mut pickle).unwrap(), pair);
+    }
+
+    #[test]
+    fn navigation_entry_round_trip(
+        tab_id in any::<i32>(),
+        index in 0..100i32,
+        url in ".*",
+        title in ".*",
+        page_state in proptest::collection::vec(any::<u8>(), 0..32),
+        transition in any::<u32>(),
+        timestamp in any::<i64>(),
+        extended in proptest::collection::hash_map("[a-z_]{1,12}", ".*", 0..4),
+        task_ids in any::<(i64, i64, i64)>(),
+    ) {
+        let mut writer = PickleWriter::new();
+        writer.write_int32(tab_id);
+        writer.write_int32(index);
+        writer.write_string(&url);
+        writer.write_string16(&title);
+        writer.write_bytes(&page_state);
+        writer.write_uint32(transition);
+        writer.write_uint32(0);
+        writer.write_string("");
+        writer.write_int32(0);
+        writer.write_string(&url);
+        writer.write_bool(false);
+        writer.write_int64(timestamp);
+        writer.write_string16("");
+        writer.write_int32(200);
+        writer.write_int32(1);
+        extended.to_pickle(&mut writer);
+        writer.write_int64(task_ids.0);
+        writer.write_int64(task_ids.1);
+        writer.write_int64(task_ids.2);
+        writer.write_int32(0);
+        let bytes = writer.into_bytes();
+
+        let mut pickle = PickleIterator::new(bytes.clone(), 4).unwrap();
+        let entry = <NavigationEntry as FromPickle>::from_pickle(&mut pickle).unwrap();
+
+        let rewritten = write(&entry);
+        prop_assert_eq!(&rewritten, &bytes);
+        let mut pickle = PickleIterator::new(rewritten, 4).unwrap();
+        prop_assert_eq!(NavigationEntry::from_pickle(&mut pickle).unwrap(), entry);
+    }
+}